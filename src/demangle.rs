@@ -0,0 +1,494 @@
+//! Demangling of MSVC C++ symbol names, such as `?foo@@YAXXZ`.
+//!
+//! Only the common subset of the scheme is understood: functions, member
+//! functions, variables, vftables and vbtables using builtin types, classes,
+//! pointers, references and templates with type or integer arguments.
+//! Anything else, such as function pointers, arrays or RTTI descriptors, is
+//! left alone. The output follows the format of `llvm-undname`.
+
+/// Demangle an MSVC C++ symbol name.
+///
+/// Returns `None` if `name` isn't mangled or uses a feature that isn't supported.
+/// An `__imp_` prefix is shown as `__declspec(dllimport)`.
+pub fn demangle(name: &str) -> Option<String> {
+    if let Some(name) = name.strip_prefix("__imp_") {
+        return demangle(name).map(|name| format!("__declspec(dllimport) {name}"));
+    }
+    let mut parser =
+        Parser { input: name.as_bytes(), pos: 0, names: Vec::new(), types: Vec::new() };
+    parser.expect(b'?')?;
+    let demangled = parser.symbol()?;
+    parser.at_end().then_some(demangled)
+}
+
+/// The special name of a member, such as a constructor or an operator.
+enum Special {
+    Constructor,
+    Destructor,
+    Vftable,
+    Vbtable,
+    Operator(&'static str),
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    /// Names that can be referred to by a digit in a qualified name.
+    names: Vec<String>,
+    /// Parameter types that can be referred to by a digit in a type.
+    types: Vec<String>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        let eaten = self.peek() == Some(b);
+        if eaten {
+            self.pos += 1;
+        }
+        eaten
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        self.eat(b).then_some(())
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos == self.input.len()
+    }
+
+    /// Parse everything after the leading `?`.
+    fn symbol(&mut self) -> Option<String> {
+        // Template names also start with `?` but are followed by `$`.
+        let special = if !self.input[self.pos..].starts_with(b"?$") && self.eat(b'?') {
+            Some(self.special()?)
+        } else {
+            None
+        };
+        let name = if special.is_none() { Some(self.name_fragment()?) } else { None };
+        let scope = self.scope()?;
+        let name = match special {
+            None => name?,
+            Some(Special::Constructor) => scope.first()?.clone(),
+            Some(Special::Destructor) => format!("~{}", scope.first()?),
+            Some(Special::Vftable) => "`vftable'".into(),
+            Some(Special::Vbtable) => "`vbtable'".into(),
+            Some(Special::Operator(op)) => format!("operator{op}"),
+        };
+        let is_structor = scope.first().is_some_and(|class| {
+            name == *class || name.strip_prefix('~').is_some_and(|name| name == class)
+        });
+        let qualified = qualify(&scope, &name);
+
+        match self.next()? {
+            // Variables. Local static variables (`4`) aren't supported.
+            code @ b'0'..=b'3' => {
+                let access = ["private: static ", "protected: static ", "public: static ", ""];
+                let ty = self.ty()?;
+                self.eat(b'E');
+                let cv = cv_qualifiers(self.next()?)?;
+                // The qualifiers of a pointer variable repeat those of what it points to,
+                // and pointers are written like `int *p`.
+                let (cv, space) = if ty.ends_with(['*', '&']) { ("", "") } else { (cv, " ") };
+                let access = access[usize::from(code - b'0')];
+                Some(format!("{access}{ty}{cv}{space}{qualified}"))
+            }
+            b'6' | b'7' => {
+                let cv = cv_qualifiers(self.next()?)?.trim_start();
+                // Tables for a specific base class end with its name, which isn't supported.
+                self.expect(b'@')?;
+                Some(if cv.is_empty() { qualified } else { format!("{cv} {qualified}") })
+            }
+            code @ b'A'..=b'X' => {
+                let index = code - b'A';
+                let access = ["private: ", "protected: ", "public: "][usize::from(index / 8)];
+                let storage = match index % 8 {
+                    0 | 1 => "",
+                    2 | 3 => "static ",
+                    4 | 5 => "virtual ",
+                    _ => return None,
+                };
+                let this = if storage == "static " { "" } else { self.this_qualifiers()? };
+                self.function(access, storage, &qualified, this, is_structor)
+            }
+            b'Y' | b'Z' => self.function("", "", &qualified, "", false),
+            _ => None,
+        }
+    }
+
+    /// Parse the name following a `?` that starts a special name.
+    fn special(&mut self) -> Option<Special> {
+        let op = match self.next()? {
+            b'0' => return Some(Special::Constructor),
+            b'1' => return Some(Special::Destructor),
+            b'2' => " new",
+            b'3' => " delete",
+            b'4' => "=",
+            b'5' => ">>",
+            b'6' => "<<",
+            b'7' => "!",
+            b'8' => "==",
+            b'9' => "!=",
+            b'A' => "[]",
+            b'C' => "->",
+            b'D' => "*",
+            b'E' => "++",
+            b'F' => "--",
+            b'G' => "-",
+            b'H' => "+",
+            b'I' => "&",
+            b'J' => "->*",
+            b'K' => "/",
+            b'L' => "%",
+            b'M' => "<",
+            b'N' => "<=",
+            b'O' => ">",
+            b'P' => ">=",
+            b'Q' => ",",
+            b'R' => "()",
+            b'S' => "~",
+            b'T' => "^",
+            b'U' => "|",
+            b'V' => "&&",
+            b'W' => "||",
+            b'X' => "*=",
+            b'Y' => "+=",
+            b'Z' => "-=",
+            b'_' => match self.next()? {
+                b'0' => "/=",
+                b'1' => "%=",
+                b'2' => ">>=",
+                b'3' => "<<=",
+                b'4' => "&=",
+                b'5' => "|=",
+                b'6' => "^=",
+                b'7' => return Some(Special::Vftable),
+                b'8' => return Some(Special::Vbtable),
+                b'U' => " new[]",
+                b'V' => " delete[]",
+                _ => return None,
+            },
+            // Conversion operators need their return type for a name.
+            _ => return None,
+        };
+        Some(Special::Operator(op))
+    }
+
+    /// Parse a name that's terminated by `@` and remember it for back references.
+    fn identifier(&mut self) -> Option<String> {
+        let len = self.input[self.pos..].iter().position(|&b| b == b'@')?;
+        let name = std::str::from_utf8(&self.input[self.pos..self.pos + len]).ok()?;
+        if name.is_empty() {
+            return None;
+        }
+        self.pos += len + 1;
+        self.remember_name(name.into());
+        Some(name.into())
+    }
+
+    fn remember_name(&mut self, name: String) {
+        if self.names.len() < 10 && !self.names.contains(&name) {
+            self.names.push(name);
+        }
+    }
+
+    /// Parse a single part of a qualified name.
+    fn name_fragment(&mut self) -> Option<String> {
+        match self.peek()? {
+            digit @ b'0'..=b'9' => {
+                self.pos += 1;
+                self.names.get(usize::from(digit - b'0')).cloned()
+            }
+            b'?' if self.input[self.pos..].starts_with(b"?$") => {
+                self.pos += 2;
+                self.template()
+            }
+            b'?' if self.input[self.pos..].starts_with(b"?A") => {
+                // An anonymous namespace has a unique name such as `?A0x1234@`.
+                self.pos += 1;
+                self.identifier()?;
+                Some("`anonymous namespace'".into())
+            }
+            b'?' => None,
+            _ => self.identifier(),
+        }
+    }
+
+    /// Parse a template name and its arguments, such as `X@H@` for `X<int>`.
+    fn template(&mut self) -> Option<String> {
+        // Templates have their own back references.
+        let names = std::mem::take(&mut self.names);
+        let types = std::mem::take(&mut self.types);
+        let result = (|| {
+            let name = self.identifier()?;
+            let mut args = Vec::new();
+            while !self.eat(b'@') {
+                if self.input[self.pos..].starts_with(b"$0") {
+                    self.pos += 2;
+                    args.push(self.number()?);
+                } else {
+                    args.push(self.parameter()?);
+                }
+            }
+            Some(format!("{name}<{}>", args.join(", ")))
+        })();
+        self.names = names;
+        self.types = types;
+        let name = result?;
+        self.remember_name(name.clone());
+        Some(name)
+    }
+
+    /// Parse an integer, such as a non-type template argument.
+    ///
+    /// `0` to `9` encode 1 to 10 and anything else is written in hexadecimal
+    /// with the digits `A` to `P`, ending with `@`. A leading `?` negates it.
+    fn number(&mut self) -> Option<String> {
+        let sign = if self.eat(b'?') { "-" } else { "" };
+        if let Some(digit @ b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+            return Some(format!("{sign}{}", digit - b'0' + 1));
+        }
+        let mut value: u64 = 0;
+        while !self.eat(b'@') {
+            let digit @ b'A'..=b'P' = self.next()? else { return None };
+            value = value.checked_mul(16)?.checked_add(u64::from(digit - b'A'))?;
+        }
+        Some(format!("{sign}{value}"))
+    }
+
+    /// Parse the enclosing scopes of a name, innermost first, up to the terminating `@`.
+    fn scope(&mut self) -> Option<Vec<String>> {
+        let mut scope = Vec::new();
+        while !self.eat(b'@') {
+            scope.push(self.name_fragment()?);
+        }
+        Some(scope)
+    }
+
+    /// Parse a qualified name such as a class name.
+    fn qualified_name(&mut self) -> Option<String> {
+        let first = self.name_fragment()?;
+        let scope = self.scope()?;
+        Some(qualify(&scope, &first))
+    }
+
+    /// Parse the qualifiers of the `this` pointer of a member function.
+    fn this_qualifiers(&mut self) -> Option<&'static str> {
+        // `E` is `__ptr64`, `I` is `__restrict` and `F` is `__unaligned`.
+        while matches!(self.peek()?, b'E' | b'I' | b'F') {
+            self.pos += 1;
+        }
+        cv_qualifiers(self.next()?)
+    }
+
+    fn function(
+        &mut self,
+        access: &str,
+        storage: &str,
+        name: &str,
+        this: &str,
+        is_structor: bool,
+    ) -> Option<String> {
+        let convention = match self.next()? {
+            b'A' | b'B' => "__cdecl",
+            b'C' | b'D' => "__pascal",
+            b'E' | b'F' => "__thiscall",
+            b'G' | b'H' => "__stdcall",
+            b'I' | b'J' => "__fastcall",
+            b'Q' => "__vectorcall",
+            _ => return None,
+        };
+        let ret = if is_structor {
+            self.expect(b'@')?;
+            String::new()
+        } else {
+            format!("{} ", self.return_type()?)
+        };
+        let params = if self.eat(b'X') {
+            "void".into()
+        } else {
+            let mut params = Vec::new();
+            loop {
+                if self.eat(b'@') {
+                    break;
+                }
+                if self.eat(b'Z') {
+                    params.push("...".into());
+                    break;
+                }
+                params.push(self.parameter()?);
+            }
+            params.join(", ")
+        };
+        // The exception specification is always empty.
+        self.expect(b'Z')?;
+        Some(format!("{access}{storage}{ret}{convention} {name}({params}){this}"))
+    }
+
+    fn return_type(&mut self) -> Option<String> {
+        if self.eat(b'?') {
+            let cv = cv_qualifiers(self.next()?)?;
+            return Some(format!("{}{cv}", self.ty()?));
+        }
+        self.ty()
+    }
+
+    /// Parse a parameter type, remembering it for back references.
+    fn parameter(&mut self) -> Option<String> {
+        let start = self.pos;
+        let ty = self.ty()?;
+        // Single character types are never back referenced.
+        if self.pos - start > 1 && self.types.len() < 10 {
+            self.types.push(ty.clone());
+        }
+        Some(ty)
+    }
+
+    fn ty(&mut self) -> Option<String> {
+        let ty = match self.next()? {
+            digit @ b'0'..=b'9' => return self.types.get(usize::from(digit - b'0')).cloned(),
+            b'C' => "signed char",
+            b'D' => "char",
+            b'E' => "unsigned char",
+            b'F' => "short",
+            b'G' => "unsigned short",
+            b'H' => "int",
+            b'I' => "unsigned int",
+            b'J' => "long",
+            b'K' => "unsigned long",
+            b'M' => "float",
+            b'N' => "double",
+            b'O' => "long double",
+            b'X' => "void",
+            b'_' => match self.next()? {
+                b'J' => "__int64",
+                b'K' => "unsigned __int64",
+                b'N' => "bool",
+                b'Q' => "char8_t",
+                b'S' => "char16_t",
+                b'U' => "char32_t",
+                b'W' => "wchar_t",
+                _ => return None,
+            },
+            b'T' => return Some(format!("union {}", self.qualified_name()?)),
+            b'U' => return Some(format!("struct {}", self.qualified_name()?)),
+            b'V' => return Some(format!("class {}", self.qualified_name()?)),
+            b'W' => {
+                // Enums are always `int` sized.
+                self.expect(b'4')?;
+                return Some(format!("enum {}", self.qualified_name()?));
+            }
+            b'P' => return self.pointer(" *"),
+            b'Q' => return self.pointer(" *const"),
+            b'R' => return self.pointer(" *volatile"),
+            b'S' => return self.pointer(" *const volatile"),
+            b'A' => return self.pointer(" &"),
+            b'B' => return self.pointer(" & volatile"),
+            b'$' if self.input[self.pos..].starts_with(b"$Q") => {
+                self.pos += 2;
+                return self.pointer(" &&");
+            }
+            _ => return None,
+        };
+        Some(ty.into())
+    }
+
+    /// Parse the type pointed to by a pointer or reference, which is written as `suffix`.
+    fn pointer(&mut self, suffix: &str) -> Option<String> {
+        // Function pointers and member pointers aren't supported.
+        if matches!(self.peek()?, b'6'..=b'9' | b'$') {
+            return None;
+        }
+        while matches!(self.peek()?, b'E' | b'I' | b'F') {
+            self.pos += 1;
+        }
+        let cv = cv_qualifiers(self.next()?)?;
+        // A pointer to a pointer gets its qualifiers from the inner pointer's own code.
+        let cv =
+            if matches!(self.peek()?, b'P' | b'Q' | b'R' | b'S' | b'A' | b'B') { "" } else { cv };
+        // Arrays aren't supported.
+        if self.peek()? == b'Y' {
+            return None;
+        }
+        Some(format!("{}{cv}{suffix}", self.ty()?))
+    }
+}
+
+/// The qualifiers written after a type for a cv code.
+fn cv_qualifiers(code: u8) -> Option<&'static str> {
+    match code {
+        b'A' => Some(""),
+        b'B' => Some(" const"),
+        b'C' => Some(" volatile"),
+        b'D' => Some(" const volatile"),
+        _ => None,
+    }
+}
+
+/// Join a name with its enclosing scopes, which are given innermost first.
+fn qualify(scope: &[String], name: &str) -> String {
+    let mut qualified: Vec<&str> = scope.iter().rev().map(String::as_str).collect();
+    qualified.push(name);
+    qualified.join("::")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::demangle;
+
+    /// Mangled names and their demangled form, as given by `llvm-undname`.
+    const VECTORS: &[(&str, &str)] = &[
+        ("?foo@@YAXXZ", "void __cdecl foo(void)"),
+        ("?bar@ns@@YAHH@Z", "int __cdecl ns::bar(int)"),
+        ("?f@@YAXH_N@Z", "void __cdecl f(int, bool)"),
+        ("?f@@YAXPEBD@Z", "void __cdecl f(char const *)"),
+        ("?x@@3HA", "int x"),
+        ("??0Foo@@QEAA@XZ", "public: __cdecl Foo::Foo(void)"),
+        ("??1Foo@@QEAA@XZ", "public: __cdecl Foo::~Foo(void)"),
+        ("?get@Foo@@QEBAHXZ", "public: int __cdecl Foo::get(void) const"),
+        (
+            "??4Foo@@QEAAAEAV0@AEBV0@@Z",
+            "public: class Foo & __cdecl Foo::operator=(class Foo const &)",
+        ),
+        ("??_7Foo@@6B@", "const Foo::`vftable'"),
+        ("?f@?A0x1234@@YAXXZ", "void __cdecl `anonymous namespace'::f(void)"),
+        ("?f@@YAXV?$vector@H@std@@@Z", "void __cdecl f(class std::vector<int>)"),
+        ("?a@@YAXAEAV?$b@$0A@@@@Z", "void __cdecl a(class b<0> &)"),
+        ("?a@@YAXAEAV?$b@$00@@@Z", "void __cdecl a(class b<1> &)"),
+        ("?a@@YAXAEAV?$b@$09@@@Z", "void __cdecl a(class b<10> &)"),
+        ("?a@@YAXAEAV?$b@$0BA@@@@Z", "void __cdecl a(class b<16> &)"),
+        ("?a@@YAXAEAV?$b@$0?0@@@Z", "void __cdecl a(class b<-1> &)"),
+        ("?a@@YAXAEAV?$b@$0?BA@@@@Z", "void __cdecl a(class b<-16> &)"),
+        ("?a@@YAXV?$b@H$0CA@@@@Z", "void __cdecl a(class b<int, 32>)"),
+    ];
+
+    #[test]
+    fn vectors() {
+        for (mangled, expected) in VECTORS {
+            assert_eq!(demangle(mangled).as_deref(), Some(*expected), "{mangled}");
+        }
+    }
+
+    #[test]
+    fn dllimport() {
+        let demangled = demangle("__imp_?foo@@YAXXZ");
+        assert_eq!(demangled.as_deref(), Some("__declspec(dllimport) void __cdecl foo(void)"));
+    }
+
+    #[test]
+    fn unsupported() {
+        for name in ["foo", "?", "?foo@@YAXXZtrailing", "?a@@YAXV?$b@$0Q@@@@Z"] {
+            assert_eq!(demangle(name), None, "{name}");
+        }
+    }
+}
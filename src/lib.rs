@@ -0,0 +1,3811 @@
+//! Tools for creating, inspecting and modifying Windows lib files.
+//!
+//! This is the library behind the `winlib` command line tool. Most functions
+//! take the path of a lib and either write a new lib or return information
+//! about the one at that path. [`LibReader`] gives access to the members of a
+//! lib for anything those functions don't cover.
+
+mod demangle;
+mod hash;
+
+pub use demangle::demangle;
+
+use ar_archive_writer::{COFFShortExport, MachineTypes, NewArchiveMember};
+use object::LittleEndian as LE;
+use object::coff::ImageSymbol;
+use object::coff::{CoffBigFile, CoffFile, CoffHeader, ImportFile, ImportName, ImportType};
+use object::pe::{self, ImageFileHeader};
+use object::read::archive::{ArchiveFile, ArchiveKind, ArchiveMember};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter, Cursor, IsTerminal, Read, Write};
+use std::mem;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Set by [`set_quiet`].
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Stop warnings, progress and other informational messages from being written
+/// to stderr. Errors are still returned as usual.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print a warning to stderr unless [`set_quiet`] has been called.
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        if !is_quiet() {
+            eprintln!("warning: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+/// An error from one of the lib operations.
+#[derive(Debug)]
+pub enum WinlibError {
+    ObjectError { msg: String, cause: object::Error },
+    IoError { msg: String, cause: io::Error },
+    InvalidInput { msg: String },
+}
+impl fmt::Display for WinlibError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ObjectError { msg, cause } => {
+                write!(f, "{msg}\ncause: {cause}")
+            }
+            Self::IoError { msg, cause } => {
+                write!(f, "{msg}\ncause: {cause}")
+            }
+            Self::InvalidInput { msg } => {
+                write!(f, "{msg}")
+            }
+        }
+    }
+}
+impl Error for WinlibError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ObjectError { cause, .. } => Some(cause),
+            Self::IoError { cause, .. } => Some(cause),
+            Self::InvalidInput { .. } => None,
+        }
+    }
+}
+impl WinlibError {
+    /// A stable name for the category of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ObjectError { .. } => "object",
+            Self::IoError { .. } => "io",
+            Self::InvalidInput { .. } => "invalid-input",
+        }
+    }
+
+    /// The process exit code used for this category of error.
+    ///
+    /// `1` is left for failed checks, such as differences found by `diff`, and
+    /// `2` for command line errors.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::InvalidInput { .. } => 3,
+            Self::IoError { .. } => 4,
+            Self::ObjectError { .. } => 5,
+        }
+    }
+
+    /// The error message, without its cause.
+    pub fn message(&self) -> &str {
+        match self {
+            Self::ObjectError { msg, .. }
+            | Self::IoError { msg, .. }
+            | Self::InvalidInput { msg } => msg,
+        }
+    }
+}
+
+/// The kind of an archive member.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemberKind {
+    /// A short import.
+    Import,
+    /// A COFF object containing `.idata$` sections.
+    ImportObject,
+    /// A COFF object containing `.didat$` sections, used for delay-loaded imports.
+    DelayImportObject,
+    /// Any other COFF object.
+    Object,
+    /// Neither a COFF object nor a short import.
+    Unknown,
+}
+impl MemberKind {
+    /// The name used for this kind of member in `list` output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Import => "import",
+            Self::ImportObject => "import-object",
+            Self::DelayImportObject => "delay-import-object",
+            Self::Object => "object",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// A hash used to identify the contents of a member.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashKind {
+    /// A SHA-256 digest.
+    Sha256,
+    /// A CRC-32 checksum, which is quicker but only suitable for spotting accidental changes.
+    Crc32,
+}
+impl HashKind {
+    /// The name used for this hash on the command line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Crc32 => "crc32",
+        }
+    }
+
+    /// Hash `data`, returning the hash as lowercase hexadecimal.
+    pub fn hash(self, data: &[u8]) -> String {
+        match self {
+            Self::Sha256 => hash::sha256(data).iter().map(|b| format!("{b:02x}")).collect(),
+            Self::Crc32 => format!("{:08x}", hash::crc32(data)),
+        }
+    }
+}
+
+/// The type of symbol a short import member imports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportKind {
+    /// A function, which also gets a thunk that calls through the import address.
+    Code,
+    /// A variable, which is only accessed through its `__imp_` symbol.
+    Data,
+    /// A constant.
+    Const,
+}
+impl ImportKind {
+    fn of(file: &ImportFile<'_>) -> Self {
+        match file.import_type() {
+            ImportType::Code => Self::Code,
+            ImportType::Data => Self::Data,
+            ImportType::Const => Self::Const,
+        }
+    }
+}
+
+/// Returns true if `data` is a short import member whose import type isn't `kind`.
+fn is_other_import_kind(data: &[u8], kind: ImportKind) -> bool {
+    ImportFile::parse(data).is_ok_and(|file| ImportKind::of(&file) != kind)
+}
+
+/// Work out what kind of member `data` is.
+///
+/// Members that can't be recognised are [`MemberKind::Unknown`].
+pub fn classify(data: &[u8]) -> MemberKind {
+    classify_member(data).unwrap_or(MemberKind::Unknown)
+}
+
+/// Work out what kind of member `data` is.
+///
+/// Unlike [`classify`], this returns an error if it's neither a COFF object
+/// nor a short import so that strict callers can report why.
+fn classify_member(data: &[u8]) -> Result<MemberKind, object::Error> {
+    // A bigobj header would otherwise be misread as a regular COFF header.
+    if is_bigobj(data) {
+        return classify_coff(&CoffBigFile::<&[u8]>::parse(data)?);
+    }
+    match CoffFile::<_, ImageFileHeader>::parse(data) {
+        Ok(file) => classify_coff(&file),
+        Err(e) => {
+            // This also accepts the short imports written by `llvm-lib` and
+            // `llvm-dlltool`, including the `EXPORTAS` name type used for ARM64EC.
+            if ImportFile::parse(data).is_ok() { Ok(MemberKind::Import) } else { Err(e) }
+        }
+    }
+}
+
+/// Whether `data` is a short import or an import object.
+///
+/// These are named after the DLL they import from, so import libs for the same
+/// DLL are expected to share member names.
+fn is_import_member(data: &[u8]) -> bool {
+    matches!(
+        classify(data),
+        MemberKind::Import | MemberKind::ImportObject | MemberKind::DelayImportObject
+    )
+}
+
+/// Whether `data` is a COFF object with the extended header used by `/bigobj`.
+fn is_bigobj(data: &[u8]) -> bool {
+    matches!(object::FileKind::parse(data), Ok(object::FileKind::CoffBig))
+}
+
+fn classify_coff<'data, C: CoffHeader>(
+    file: &CoffFile<'data, &'data [u8], C>,
+) -> Result<MemberKind, object::Error> {
+    let strings = file.coff_symbol_table().strings();
+    for section in file.coff_section_table().iter() {
+        let name = section.name(strings)?;
+        if name.starts_with(b".idata$") {
+            return Ok(MemberKind::ImportObject);
+        } else if name.starts_with(b".didat$") {
+            return Ok(MemberKind::DelayImportObject);
+        }
+    }
+    Ok(MemberKind::Object)
+}
+
+/// Classify each member, splitting the work between up to `jobs` threads.
+///
+/// The results are in the same order as `members`.
+fn classify_members(
+    members: &[&[u8]],
+    jobs: usize,
+    progress: Option<&Progress>,
+) -> Vec<Result<MemberKind, object::Error>> {
+    let classify = |data: &&[u8]| {
+        let kind = classify_member(data);
+        if let Some(progress) = progress {
+            progress.tick();
+        }
+        kind
+    };
+    let chunk_size = members.len().div_ceil(jobs.max(1)).max(1);
+    if chunk_size >= members.len() {
+        return members.iter().map(classify).collect();
+    }
+    thread::scope(|scope| {
+        let threads: Vec<_> = members
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(classify).collect::<Vec<_>>()))
+            .collect();
+        threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+/// A count of processed members that's periodically printed to stderr.
+struct Progress {
+    action: &'static str,
+    total: usize,
+    done: AtomicUsize,
+    last_report: Mutex<Instant>,
+    terminal: bool,
+}
+impl Progress {
+    fn new(action: &'static str, total: usize) -> Self {
+        Self {
+            action,
+            total,
+            done: AtomicUsize::new(0),
+            last_report: Mutex::new(Instant::now()),
+            terminal: io::stderr().is_terminal(),
+        }
+    }
+
+    /// Count a member, printing the count if enough time has passed since it was last printed.
+    ///
+    /// A terminal has the line updated in place whereas other outputs get a new
+    /// line every second.
+    fn tick(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let interval = Duration::from_millis(if self.terminal { 100 } else { 1000 });
+        // Another thread is already reporting.
+        let Ok(mut last_report) = self.last_report.try_lock() else { return };
+        if last_report.elapsed() >= interval {
+            *last_report = Instant::now();
+            self.report(done);
+        }
+    }
+
+    fn finish(self) {
+        self.report(self.done.load(Ordering::Relaxed));
+        if self.terminal {
+            eprintln!();
+        }
+    }
+
+    fn report(&self, done: usize) {
+        let (action, total) = (self.action, self.total);
+        if self.terminal {
+            eprint!("\r{action} {done}/{total} members");
+        } else {
+            eprintln!("{action} {done}/{total} members");
+        }
+    }
+}
+
+/// How long a command took and how much it read, as printed by `--stats`.
+pub struct Stats {
+    /// The wall-clock time of the whole command.
+    pub elapsed: Duration,
+    /// The number of members processed.
+    pub members: usize,
+    /// The number of bytes read.
+    pub bytes: u64,
+    /// The time spent parsing members to classify them, if they were.
+    pub parse_time: Option<Duration>,
+}
+impl Stats {
+    /// Print the stats to stderr, unless quiet.
+    pub fn print(&self) {
+        if is_quiet() {
+            return;
+        }
+        let Self { elapsed, members, bytes, .. } = self;
+        let secs = elapsed.as_secs_f64();
+        let plural = if *members == 1 { "" } else { "s" };
+        eprint!("{members} member{plural}, {bytes:#X} bytes read in {secs:.3}s");
+        if secs > 0.0 {
+            eprint!(" ({:.1} MB/s)", *bytes as f64 / 1e6 / secs);
+        }
+        eprintln!();
+        if let Some(parse_time) = self.parse_time {
+            eprintln!("{:.3}s parsing COFF members", parse_time.as_secs_f64());
+        }
+    }
+}
+
+/// The contents of an input file.
+enum FileData {
+    Mapped(memmap2::Mmap),
+    Read(Vec<u8>),
+}
+impl std::ops::Deref for FileData {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped(map) => map,
+            Self::Read(data) => data,
+        }
+    }
+}
+
+/// Memory map the file at `path`, or read it into memory if it can't be mapped.
+///
+/// Pages of a mapped file are only loaded when they're first read, so parts
+/// of a lib that are never looked at, such as the data of members whose
+/// headers are all that's needed, aren't read from disk. A path of `-` reads
+/// all of stdin into memory.
+fn read_file(path: &OsStr) -> Result<FileData, WinlibError> {
+    if path == "-" {
+        let mut data = Vec::new();
+        io::stdin()
+            .lock()
+            .read_to_end(&mut data)
+            .map_err(|e| WinlibError::IoError { msg: "cannot read from stdin".into(), cause: e })?;
+        return Ok(FileData::Read(data));
+    }
+    let error =
+        |e| WinlibError::IoError { msg: format!("cannot read {}", path.display()), cause: e };
+    let file = fs::File::open(path).map_err(error)?;
+    // SAFETY: the mapping is read-only and outputs are only written after all
+    // reads from it are finished. Modifying the file from another process while
+    // it's mapped is undefined behaviour, which is an accepted risk of mapping.
+    match unsafe { memmap2::Mmap::map(&file) } {
+        Ok(map) => Ok(FileData::Mapped(map)),
+        Err(_) => {
+            let mut data = Vec::new();
+            (&file).read_to_end(&mut data).map_err(error)?;
+            Ok(FileData::Read(data))
+        }
+    }
+}
+
+/// Buffer output to stdout, which would otherwise be flushed after every line.
+///
+/// The writer must be flushed explicitly so that errors aren't lost on drop.
+fn buffered_stdout() -> BufWriter<io::StdoutLock<'static>> {
+    BufWriter::new(io::stdout().lock())
+}
+
+fn stdout_error(cause: io::Error) -> WinlibError {
+    WinlibError::IoError { msg: "unable to write to stdout".into(), cause }
+}
+
+/// Write a lib to `path`, or to stdout if `path` is `-`.
+///
+/// Files are written with [`replace_lib`] so an interrupted write never leaves
+/// a partial lib behind.
+fn write_lib(path: &OsStr, data: &[u8]) -> Result<(), WinlibError> {
+    if path != "-" {
+        return replace_lib(path, data);
+    }
+    io::stdout().lock().write_all(data).map_err(|e| WinlibError::IoError {
+        msg: format!("unable to write library to {}", path.display()),
+        cause: e,
+    })
+}
+
+/// Write a lib to a temporary file next to `path` and then rename it to `path`.
+///
+/// This means `path` is never left partially written. The temporary file is
+/// named after `path`, the process id and a counter, and is created
+/// exclusively so an existing file is never overwritten. It's removed if
+/// either step fails.
+fn replace_lib(path: &OsStr, data: &[u8]) -> Result<(), WinlibError> {
+    static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+    let mut temp_path = path.to_owned();
+    let count = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+    temp_path.push(format!(".{}.{count}.tmp", std::process::id()));
+    let mut file =
+        fs::OpenOptions::new().write(true).create_new(true).open(&temp_path).map_err(|e| {
+            WinlibError::IoError {
+                msg: format!("unable to create the temporary file {}", temp_path.display()),
+                cause: e,
+            }
+        })?;
+    file.write_all(data).and_then(|()| file.flush()).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        WinlibError::IoError {
+            msg: format!("unable to write library to {}", temp_path.display()),
+            cause: e,
+        }
+    })?;
+    // The file must be closed before it can be renamed on Windows.
+    drop(file);
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        WinlibError::IoError {
+            msg: format!("unable to replace {} with {}", path.display(), temp_path.display()),
+            cause: e,
+        }
+    })
+}
+
+/// The data of the members of a thin archive, which are stored in separate files.
+///
+/// A thin member's name is the path of its file, relative to the archive.
+/// The data is stored by name because `object` reports an offset of 0 for
+/// every thin member. This is empty for other archives.
+struct ThinMembers(HashMap<Vec<u8>, FileData>);
+impl ThinMembers {
+    /// Read the file of each thin member of `archive`, which was read from `lib_path`.
+    fn load(archive: &ArchiveFile<'_>, lib_path: &OsStr) -> Result<Self, WinlibError> {
+        let mut files = HashMap::new();
+        if !archive.is_thin() {
+            return Ok(Self(files));
+        }
+        for member in archive.members() {
+            let member = member.map_err(|e| WinlibError::ObjectError {
+                msg: format!("could not read archive member in {}", lib_path.display()),
+                cause: e,
+            })?;
+            if !member.is_thin() {
+                continue;
+            }
+            let path = thin_member_path(lib_path, member.name());
+            let data = read_file(path.as_os_str()).map_err(|e| match e {
+                WinlibError::IoError { cause, .. } => WinlibError::IoError {
+                    msg: format!(
+                        "cannot read {} for a member of the thin archive {}",
+                        path.display(),
+                        lib_path.display()
+                    ),
+                    cause,
+                },
+                e => e,
+            })?;
+            files.insert(member.name().to_vec(), data);
+        }
+        Ok(Self(files))
+    }
+
+    /// Get the data of `member`, from its own file if it's a thin member.
+    fn data<'a>(&'a self, member: &ArchiveMember<'a>, data: &'a [u8]) -> object::Result<&'a [u8]> {
+        match self.0.get(member.name()) {
+            Some(file) if member.is_thin() => Ok(file),
+            _ => member.data(data),
+        }
+    }
+}
+
+/// A lib that has been read into memory, including the files of its members if
+/// it's a thin archive.
+pub struct LibReader {
+    path: OsString,
+    data: FileData,
+    thin: ThinMembers,
+}
+impl LibReader {
+    /// Read the lib at `path`, or from stdin if `path` is `-`.
+    pub fn open(path: &OsStr) -> Result<Self, WinlibError> {
+        let data = read_file(path)?;
+        let thin = ThinMembers::load(&parse_archive(&data, path)?, path)?;
+        Ok(Self { path: path.to_owned(), data, thin })
+    }
+
+    /// The path the lib was read from.
+    pub fn path(&self) -> &OsStr {
+        &self.path
+    }
+
+    /// The archive format of the lib.
+    pub fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::of(&self.archive())
+    }
+
+    fn archive(&self) -> ArchiveFile<'_> {
+        // This can't fail because the same data already parsed in `open`.
+        parse_archive(&self.data, &self.path).expect("the lib was parsed when it was opened")
+    }
+
+    /// Iterate over the members of the lib in the order they're stored.
+    ///
+    /// The symbol index, long name table and EC symbol map aren't members,
+    /// wherever they are in the lib.
+    pub fn members(&self) -> impl Iterator<Item = Result<Member<'_>, WinlibError>> {
+        let members = self.archive().members();
+        members.filter(|member| !member.as_ref().is_ok_and(is_special_member)).map(move |member| {
+            let member = member.map_err(|e| WinlibError::ObjectError {
+                msg: format!("could not read archive member in {}", self.path.display()),
+                cause: e,
+            })?;
+            Ok(Member { member, lib: self })
+        })
+    }
+}
+
+/// A member of a lib read by [`LibReader`].
+///
+/// The member's data is only parsed when one of the methods that need it is called.
+pub struct Member<'a> {
+    member: ArchiveMember<'a>,
+    lib: &'a LibReader,
+}
+impl<'a> Member<'a> {
+    /// The member's full name, with any long name table reference resolved.
+    pub fn name(&self) -> Cow<'a, str> {
+        String::from_utf8_lossy(self.member.name())
+    }
+
+    /// The offset of the member's data in the lib, which is 0 for thin members.
+    pub fn offset(&self) -> u64 {
+        self.member.file_range().0
+    }
+
+    /// The size of the member's data.
+    pub fn size(&self) -> u64 {
+        self.member.file_range().1
+    }
+
+    /// The member's data, which is read from its own file for thin members.
+    pub fn data(&self) -> Result<&'a [u8], WinlibError> {
+        self.lib.thin.data(&self.member, &self.lib.data).map_err(|e| WinlibError::ObjectError {
+            msg: format!(
+                "could not get data from archive member at {:#x} in {}",
+                self.offset(),
+                self.lib.path.display()
+            ),
+            cause: e,
+        })
+    }
+
+    /// What kind of member this is.
+    pub fn kind(&self) -> Result<MemberKind, WinlibError> {
+        Ok(classify(self.data()?))
+    }
+
+    /// The symbols provided by the member, or `None` if it's neither a COFF
+    /// object nor a short import.
+    pub fn symbols(&self) -> Result<Option<Vec<MemberSymbol>>, WinlibError> {
+        Ok(member_symbols(self.data()?))
+    }
+}
+
+/// The path of the file of a thin member named `name` in the archive at `lib_path`.
+fn thin_member_path(lib_path: &OsStr, name: &[u8]) -> PathBuf {
+    let dir = Path::new(lib_path).parent().unwrap_or(Path::new(""));
+    dir.join(&*String::from_utf8_lossy(name))
+}
+
+/// The path of `path` relative to the directory `dir`.
+///
+/// Returns `path` unchanged if either can't be resolved or they have nothing
+/// in common, such as being on different drives.
+fn relative_path(dir: &Path, path: &Path) -> PathBuf {
+    let (Ok(dir), Ok(full_path)) = (fs::canonicalize(dir), fs::canonicalize(path)) else {
+        return path.to_owned();
+    };
+    let common = dir.components().zip(full_path.components()).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return path.to_owned();
+    }
+    let mut relative = PathBuf::new();
+    for _ in dir.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(full_path.components().skip(common));
+    relative
+}
+
+fn parse_archive<'data>(
+    data: &'data [u8],
+    path: &OsStr,
+) -> Result<ArchiveFile<'data>, WinlibError> {
+    if data.is_empty() {
+        return Err(WinlibError::InvalidInput {
+            msg: format!("{} is empty, it isn't a lib or archive file", path.display()),
+        });
+    }
+    ArchiveFile::parse(data).map_err(|e| {
+        let guess = guess_archive_kind(data)
+            .map_or(String::new(), |kind| format!(" (it looks like a damaged {kind} archive)"));
+        WinlibError::ObjectError {
+            msg: format!("not a recognised archive file: {}{guess}", path.display()),
+            cause: e,
+        }
+    })
+}
+
+/// Guess the kind of an archive that couldn't be parsed from its signature and
+/// the names of its first members.
+fn guess_archive_kind(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"!<thin>\n") {
+        return Some("thin GNU");
+    } else if data.starts_with(b"<bigaf>\n") {
+        return Some("AIX big");
+    }
+    let header = data.strip_prefix(b"!<arch>\n")?.get(..60)?;
+    match header[..16].trim_ascii_end() {
+        // COFF archives have a second index member, also named `/`, and GNU archives don't.
+        b"/" => {
+            let next_name = || {
+                let size: usize =
+                    std::str::from_utf8(&header[48..58]).ok()?.trim_end().parse().ok()?;
+                let next = 68usize.checked_add(size)?.checked_add(size % 2)?;
+                Some(data.get(next..next.checked_add(16)?)?.trim_ascii_end())
+            };
+            Some(match next_name() {
+                Some(b"/") => "COFF",
+                Some(_) => "GNU",
+                None => "COFF or GNU",
+            })
+        }
+        b"//" => Some("GNU"),
+        b"/SYM64/" => Some("64-bit GNU"),
+        b"__.SYMDEF" | b"__.SYMDEF SORTED" => Some("BSD"),
+        _ => None,
+    }
+}
+
+/// Whether `member` is a symbol index, long name table or EC symbol table.
+///
+/// `object` only skips these when they're at the start of the archive in the
+/// order it expects, such as a `/<ECSYMBOLS>/` member in a GNU archive. The
+/// writer creates its own, so copying one would leave the new lib with two.
+fn is_special_member(member: &ArchiveMember<'_>) -> bool {
+    matches!(member.name(), b"/" | b"//" | b"/SYM64/" | b"/<ECSYMBOLS>/")
+}
+
+/// Copy an existing archive member so it can be written to a new archive.
+///
+/// `object` resolves `/<offset>` references through the archive's long name
+/// table, so `member.name()` is always the full name. The writer creates a
+/// new long name table for names that don't fit in the header.
+fn new_archive_member<'data>(
+    member: &ArchiveMember<'data>,
+    data: &'data [u8],
+) -> NewArchiveMember<'data> {
+    NewArchiveMember {
+        buf: Box::new(data),
+        object_reader: &COFF_OBJECT_READER,
+        member_name: String::from_utf8_lossy(member.name()).into(),
+        mtime: member.date().unwrap_or(0),
+        uid: member.uid().unwrap_or(0) as u32,
+        gid: member.gid().unwrap_or(0) as u32,
+        perms: member.mode().unwrap_or(0o644) as u32,
+    }
+}
+
+/// The default object reader, except that it also reads the symbols of
+/// ARM64X objects, which `object` doesn't recognise.
+const COFF_OBJECT_READER: ar_archive_writer::ObjectReader = ar_archive_writer::ObjectReader {
+    get_symbols: |data, f| {
+        if member_machine(data) == Some(pe::IMAGE_FILE_MACHINE_ARM64X)
+            && let Some(symbols) = member_symbols(data)
+        {
+            for symbol in symbols {
+                f(symbol.name().as_bytes())?;
+            }
+            return Ok(true);
+        }
+        (ar_archive_writer::DEFAULT_OBJECT_READER.get_symbols)(data, f)
+    },
+    ..ar_archive_writer::DEFAULT_OBJECT_READER
+};
+
+/// Whether a lib with these members needs an `/<ECSYMBOLS>/` member, the
+/// symbol map that ARM64EC code is linked against.
+///
+/// `llvm-lib` writes one for ARM64EC and ARM64X libs. Only COFF archives can
+/// have one.
+fn needs_ec_symbols(
+    members: &[NewArchiveMember<'_>],
+    kind: ar_archive_writer::ArchiveKind,
+) -> bool {
+    use pe::{IMAGE_FILE_MACHINE_ARM64EC as ARM64EC, IMAGE_FILE_MACHINE_ARM64X as ARM64X};
+    matches!(kind, ar_archive_writer::ArchiveKind::Coff)
+        && members
+            .iter()
+            .any(|member| matches!(member_machine((*member.buf).as_ref()), Some(ARM64EC | ARM64X)))
+}
+
+/// An object reader that reports no symbols for any member.
+///
+/// Using this for every member leaves the archive's symbol index empty. The
+/// index member itself is still written because COFF linkers expect it.
+const NO_SYMBOLS_READER: ar_archive_writer::ObjectReader = ar_archive_writer::ObjectReader {
+    get_symbols: |_, _| Ok(false),
+    ..ar_archive_writer::DEFAULT_OBJECT_READER
+};
+
+/// A member of a lib, given by its name or the offset of its data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MemberRef {
+    /// Every member with this name.
+    Name(String),
+    /// The member whose data starts at this offset, as shown by `list`.
+    Offset(u64),
+}
+impl fmt::Display for MemberRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => write!(f, "{name}"),
+            Self::Offset(offset) => write!(f, "{offset:#X}"),
+        }
+    }
+}
+
+/// A pattern for matching member, symbol or DLL names.
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// A wildcard pattern.
+    ///
+    /// Member names are matched case-insensitively and may use `*` and `?`
+    /// anywhere. Symbol names are matched case-sensitively and may only end
+    /// with `*`. DLL names must match exactly, ignoring case.
+    Glob(String),
+    /// A regular expression that may match any part of the name.
+    Regex(regex_lite::Regex),
+}
+impl Pattern {
+    /// Parse a regular expression pattern.
+    pub fn regex(pattern: &str) -> Result<Self, regex_lite::Error> {
+        regex_lite::Regex::new(pattern).map(Self::Regex)
+    }
+
+    fn matches_name(&self, name: &str) -> bool {
+        match self {
+            Self::Glob(pattern) => glob_match(pattern, name),
+            Self::Regex(regex) => regex.is_match(name),
+        }
+    }
+
+    fn matches_symbol(&self, symbol: &str) -> bool {
+        match self {
+            Self::Glob(pattern) => symbol_match(pattern, symbol),
+            Self::Regex(regex) => regex.is_match(symbol),
+        }
+    }
+
+    fn matches_dll(&self, dll: &[u8]) -> bool {
+        match self {
+            Self::Glob(pattern) => pattern.as_bytes().eq_ignore_ascii_case(dll),
+            Self::Regex(regex) => regex.is_match(&String::from_utf8_lossy(dll)),
+        }
+    }
+}
+
+/// Match a symbol name against `pattern`, which may end with a `*` wildcard.
+///
+/// Unlike member names, symbol names are case-sensitive.
+fn symbol_match(pattern: &str, symbol: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => symbol.starts_with(prefix),
+        None => symbol == pattern,
+    }
+}
+
+/// The archive format written by `create`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// The format used by MSVC `lib.exe` and `link.exe`.
+    Coff,
+    /// The format used by GNU `ar` and MinGW `ld`.
+    Gnu,
+}
+
+impl ArchiveFormat {
+    /// The format to use when writing members from `archive`.
+    ///
+    /// Formats other than GNU, such as archives without a symbol index, are written as COFF.
+    fn of(archive: &ArchiveFile<'_>) -> Self {
+        match archive.kind() {
+            ArchiveKind::Gnu | ArchiveKind::Gnu64 => Self::Gnu,
+            _ => Self::Coff,
+        }
+    }
+
+    fn kind(self) -> ar_archive_writer::ArchiveKind {
+        match self {
+            Self::Coff => ar_archive_writer::ArchiveKind::Coff,
+            Self::Gnu => ar_archive_writer::ArchiveKind::Gnu,
+        }
+    }
+}
+
+/// Options for [`create_lib`]. The default copies every member.
+#[derive(Default)]
+pub struct CreateOptions {
+    /// Exclude short imports and objects containing `.idata$` or `.didat$` sections.
+    pub exclude_idata: bool,
+    /// Inclusive ranges of member offsets. A single offset is a range of one.
+    pub exclude_offsets: Vec<RangeInclusive<u64>>,
+    /// If not empty, only members whose name matches one of these wildcard
+    /// patterns are included.
+    pub include_names: Vec<Pattern>,
+    /// If given, only these members are included and they're written in this
+    /// order. It's an error if one of them isn't found.
+    pub member_list: Option<Vec<MemberRef>>,
+    /// Wildcard patterns matched against member names.
+    pub exclude_names: Vec<Pattern>,
+    /// Symbol names, optionally ending with a `*` wildcard.
+    pub exclude_symbols: Vec<Pattern>,
+    /// DLL names, matched case-insensitively against short import members.
+    pub exclude_dlls: Vec<Pattern>,
+    /// Section name prefixes. COFF objects with a section whose name starts
+    /// with one of these are excluded. Short imports have no sections.
+    pub exclude_sections: Vec<String>,
+    /// Write the excluded members to a lib at this path.
+    pub save_excluded: Option<OsString>,
+    /// Write the new lib as a thin archive that refers to the files of the
+    /// members of thin old libs instead of containing their data.
+    pub write_thin: bool,
+    /// Write a JSON description of which members were included, which were
+    /// excluded and why, and the members of the new lib to this path.
+    pub output_manifest: Option<OsString>,
+    /// Also write the included members to a GNU format lib at this path.
+    /// Only valid when `archive_format` is COFF.
+    pub also_gnu: Option<OsString>,
+    /// Print which members would be included or excluded instead of writing anything.
+    pub dry_run: bool,
+    /// Keep members that can't be classified instead of failing.
+    pub skip_unrecognized: bool,
+    /// Leave the archive's symbol index empty.
+    pub no_symbol_index: bool,
+    /// Zero the timestamp, user id and group id of every member and use the default mode.
+    pub deterministic: bool,
+    /// The format of the written libs. Defaults to the format of the first old lib.
+    pub archive_format: Option<ArchiveFormat>,
+    /// The number of threads used to classify members. Zero is treated as one.
+    pub jobs: usize,
+    /// Allow replacing existing files, including `from_lib` itself.
+    pub force: bool,
+    /// Write the new lib to a temporary file and then rename it to `out_lib`,
+    /// so `out_lib` can be `from_lib`.
+    pub in_place: bool,
+    /// Report how many members have been processed to stderr.
+    pub progress: bool,
+    /// Print the time taken and the amount read to stderr.
+    pub stats: bool,
+    /// Sort the members by name. The `save_excluded` lib is sorted in the same way.
+    pub sort: bool,
+    /// Drop members with the same name and data as a member that's already included.
+    pub dedupe: bool,
+    /// Exclude COFF objects that don't define any symbols other than weak externals.
+    pub exclude_empty: bool,
+    /// Keep the members of the existing lib at `out_lib`, if there is one, and
+    /// add the included members after them.
+    pub append: bool,
+    /// With `append`, keep new members even if the existing lib has a member
+    /// with the same name.
+    pub allow_duplicates: bool,
+    /// Pairs of old and new DLL names. Import members that import from an old
+    /// DLL, ignoring case, are changed to import from the new DLL. This
+    /// includes the import descriptor and null thunk objects of the DLL.
+    pub rename_dlls: Vec<(String, String)>,
+    /// Only include members for this COFF machine type. Members that don't
+    /// target a specific machine are always included.
+    pub machine: Option<u16>,
+    /// With `machine`, exclude members whose machine type can't be read
+    /// instead of including them.
+    pub drop_unparseable: bool,
+    /// Warn about included members for a different COFF machine type, or fail if `strict` is set.
+    pub target: Option<u16>,
+    /// Fail, rather than warn, if an offset in `exclude_offsets` doesn't match any member,
+    /// an included member doesn't match `target` or every member is excluded.
+    pub strict: bool,
+    /// Leave the modification time, user id, group id and mode of a member blank
+    /// if they were blank in the old lib, instead of writing a default value.
+    pub preserve_metadata: bool,
+    /// Remove the sections whose name matches one of these patterns from COFF
+    /// members. The patterns are matched like `exclude_symbols`.
+    pub strip_sections: Vec<Pattern>,
+    /// Exclude short import members that don't import this type of symbol.
+    /// Other members are kept.
+    pub import_kind: Option<ImportKind>,
+    /// Fail if a member of an old lib is larger than this many bytes.
+    pub max_member_size: Option<u64>,
+}
+
+/// Quote a string for use in JSON output, such as the manifest written by
+/// [`create_lib`].
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Why [`create_lib`] excluded a member, as written to the manifest.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ExcludeReason {
+    NotIncluded,
+    NotListed,
+    Offset,
+    Name,
+    Symbol,
+    Dll,
+    Machine,
+    ImportType,
+    Section,
+    Idata,
+    Empty,
+    SameName,
+    Duplicate,
+}
+
+impl ExcludeReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::NotIncluded => "not-included",
+            Self::NotListed => "not-listed",
+            Self::Offset => "offset",
+            Self::Name => "name",
+            Self::Symbol => "symbol",
+            Self::Dll => "dll",
+            Self::Machine => "machine",
+            Self::ImportType => "import-type",
+            Self::Section => "section",
+            Self::Idata => "idata",
+            Self::Empty => "empty",
+            Self::SameName => "same-name",
+            Self::Duplicate => "duplicate",
+        }
+    }
+
+    /// Why a member was excluded, as the end of a sentence starting with the member.
+    fn description(self) -> &'static str {
+        match self {
+            Self::NotIncluded => "didn't match --include",
+            Self::NotListed => "didn't match --members-from",
+            Self::Offset => "matched --exclude",
+            Self::Name => "matched an excluded name pattern",
+            Self::Symbol => "matched --exclude-symbol",
+            Self::Dll => "matched --exclude-dll",
+            Self::Machine => "didn't match --machine",
+            Self::ImportType => "didn't match --import-type",
+            Self::Section => "matched --exclude-section",
+            Self::Idata => "matched --exclude-idata",
+            Self::Empty => "matched --exclude-empty",
+            Self::SameName => "had the same name as a member of an earlier lib",
+            Self::Duplicate => "matched --dedupe",
+        }
+    }
+}
+
+/// The JSON manifest written by `create_lib` for `output_manifest`.
+///
+/// Each of `selected` is the index of the old lib, the offset and name of a
+/// member and why it was excluded, if it was.
+fn create_manifest(
+    from_libs: &[OsString],
+    out_lib: &OsStr,
+    selected: &[(usize, u64, String, Option<ExcludeReason>)],
+    members: &[NewArchiveMember],
+) -> String {
+    let lib = |index: usize| json_string(&from_libs[index].to_string_lossy());
+    let entries = |excluded: bool| {
+        let entries: Vec<_> = selected
+            .iter()
+            .filter(|(.., reason)| reason.is_some() == excluded)
+            .map(|(index, offset, name, reason)| {
+                let mut entry = format!(
+                    r#"{{"lib": {}, "offset": {offset}, "name": {}"#,
+                    lib(*index),
+                    json_string(name)
+                );
+                if let Some(reason) = reason {
+                    entry.push_str(&format!(r#", "reason": "{}""#, reason.as_str()));
+                }
+                format!("\n    {entry}}}")
+            })
+            .collect();
+        entries.join(",")
+    };
+    let from: Vec<_> = (0..from_libs.len()).map(lib).collect();
+    let members: Vec<_> = members
+        .iter()
+        .map(|member| format!("\n    {}", json_string(&member.member_name)))
+        .collect();
+    format!(
+        "{{\n  \"from\": [{}],\n  \"out\": {},\n  \"included\": [{}\n  ],\n  \"excluded\": [{}\n  ],\n  \"members\": [{}\n  ]\n}}\n",
+        from.join(", "),
+        json_string(&out_lib.to_string_lossy()),
+        entries(false),
+        entries(true),
+        members.join(",")
+    )
+}
+
+/// Options for [`merge_libs`].
+#[derive(Default)]
+pub struct MergeOptions {
+    /// Keep members even if a member with the same name is in another lib.
+    pub allow_duplicates: bool,
+    /// Sort the members by name instead of keeping them in the order of the libs.
+    pub sort: bool,
+    /// Drop members with the same name and data as a member that's already included.
+    /// These are never treated as duplicates between libs.
+    pub dedupe: bool,
+    /// The archive format to write. Defaults to the format of the first lib.
+    pub archive_format: Option<ArchiveFormat>,
+    /// Overwrite the new lib if it already exists, even if it's one of the libs.
+    pub force: bool,
+}
+
+/// Fail if writing to `out_path` would replace an existing file, which was
+/// made from `from_lib`.
+///
+/// Standard output, given as `-`, never exists.
+pub fn check_overwrite(from_lib: &OsStr, out_path: &OsStr) -> Result<(), WinlibError> {
+    if out_path == "-" {
+        return Ok(());
+    }
+    let exists = |path| Path::new(path).try_exists();
+    let error = |msg, cause| WinlibError::IoError { msg, cause };
+    match exists(out_path) {
+        Ok(false) => Ok(()),
+        Ok(true) => {
+            let same = match (fs::canonicalize(from_lib), fs::canonicalize(out_path)) {
+                (Ok(from), Ok(out)) => from == out,
+                _ => false,
+            };
+            let msg = if same {
+                format!("refusing to overwrite the input lib {}", out_path.display())
+            } else {
+                format!("refusing to overwrite {}", out_path.display())
+            };
+            Err(error(
+                format!("{msg} without --force"),
+                io::Error::from(io::ErrorKind::AlreadyExists),
+            ))
+        }
+        Err(e) => Err(error(format!("cannot check whether {} exists", out_path.display()), e)),
+    }
+}
+
+/// Rewrite a short import member so it imports from `dll`.
+///
+/// Returns `None` if the member is malformed.
+fn rename_import_dll(data: &[u8], dll: &str) -> Option<Vec<u8>> {
+    // The header is followed by the null terminated symbol and DLL names and
+    // then, for some name types, the null terminated export name.
+    let header_size = mem::size_of::<pe::ImportObjectHeader>();
+    let names = data.get(header_size..)?;
+    let symbol_end = names.iter().position(|&b| b == 0)?;
+    let dll_end = symbol_end + 1 + names[symbol_end + 1..].iter().position(|&b| b == 0)?;
+
+    let mut renamed = Vec::with_capacity(data.len() + dll.len());
+    renamed.extend_from_slice(&data[..header_size]);
+    renamed.extend_from_slice(&names[..=symbol_end]);
+    renamed.extend_from_slice(dll.as_bytes());
+    renamed.push(0);
+    renamed.extend_from_slice(&names[dll_end + 1..]);
+    // Update `SizeOfData`.
+    let size_of_data = u32::try_from(renamed.len() - header_size).ok()?;
+    renamed[12..16].copy_from_slice(&size_of_data.to_le_bytes());
+    Some(renamed)
+}
+
+/// Rewrite a member that imports from the old DLL of one of `renames` so it
+/// imports from the matching new DLL instead.
+///
+/// Short imports are edited in place. The import descriptor and null thunk
+/// objects of an import lib are named after the DLL, so they're replaced with
+/// ones generated for the new DLL. Returns `Ok(None)` if the member doesn't
+/// import from any of the old DLLs. Fails for other import objects that refer
+/// to an old DLL, such as those written by GNU dlltool.
+fn rename_dll(data: &[u8], renames: &[(String, String)]) -> Result<Option<Vec<u8>>, String> {
+    let is_old = |old: &str, dll: &[u8]| old.as_bytes().eq_ignore_ascii_case(dll);
+    if let Ok(file) = ImportFile::parse(data) {
+        let Some((_, dll)) = renames.iter().find(|(old, _)| is_old(old, file.dll())) else {
+            return Ok(None);
+        };
+        return rename_import_dll(data, dll)
+            .map(Some)
+            .ok_or_else(|| "malformed short import".into());
+    }
+    if classify(data) != MemberKind::ImportObject {
+        return Ok(None);
+    }
+    let symbols = member_symbols(data).unwrap_or_default();
+    let imports = member_imports(data).unwrap_or_default();
+    for (old, new) in renames {
+        let stem = Path::new(old).file_stem().and_then(OsStr::to_str).unwrap_or(old);
+        let defines = |wanted: &str| symbols.iter().any(|s| s.name().eq_ignore_ascii_case(wanted));
+        // The members of an import lib made by `write_import_library`, in order.
+        let index = if defines(&format!("__IMPORT_DESCRIPTOR_{stem}")) {
+            0
+        } else if defines(&format!("\x7f{stem}_NULL_THUNK_DATA")) {
+            2
+        } else if imports.iter().any(|import| match import {
+            ImportEntry::Descriptor(dll) | ImportEntry::DllName(dll) => is_old(old, dll.as_bytes()),
+            _ => false,
+        }) {
+            return Err(format!("only MSVC-style import objects for {old} can be renamed"));
+        } else {
+            continue;
+        };
+        // `write_import_library` names the new objects after the DLL too.
+        let machine = match member_machine(data) {
+            Some(pe::IMAGE_FILE_MACHINE_I386) => MachineTypes::I386,
+            Some(pe::IMAGE_FILE_MACHINE_AMD64) => MachineTypes::AMD64,
+            Some(pe::IMAGE_FILE_MACHINE_ARMNT) => MachineTypes::ARMNT,
+            Some(pe::IMAGE_FILE_MACHINE_ARM64) => MachineTypes::ARM64,
+            Some(pe::IMAGE_FILE_MACHINE_ARM64EC) => MachineTypes::ARM64EC,
+            Some(pe::IMAGE_FILE_MACHINE_ARM64X) => MachineTypes::ARM64X,
+            Some(machine) => return Err(format!("unsupported machine type {machine:#x}")),
+            None => return Err("unreadable machine type".into()),
+        };
+        let mut lib = Cursor::new(Vec::new());
+        ar_archive_writer::write_import_library(&mut lib, new, &[], machine, false, false)
+            .map_err(|e| e.to_string())?;
+        let lib = lib.into_inner();
+        let archive = ArchiveFile::parse(lib.as_slice()).map_err(|e| e.to_string())?;
+        let member = archive
+            .members()
+            .filter(|member| member.as_ref().is_ok_and(|member| !is_special_member(member)))
+            .nth(index)
+            .ok_or("the new import lib has too few members")?
+            .map_err(|e| e.to_string())?;
+        return Ok(Some(member.data(lib.as_slice()).map_err(|e| e.to_string())?.to_vec()));
+    }
+    Ok(None)
+}
+
+/// Why [`strip_sections`] couldn't rewrite an object.
+enum StripError {
+    /// `object` couldn't read part of the object.
+    Object(object::Error),
+    /// The sections can't be removed, or the object is malformed in a way
+    /// that `object` doesn't check.
+    Invalid(String),
+}
+impl From<object::Error> for StripError {
+    fn from(e: object::Error) -> Self {
+        Self::Object(e)
+    }
+}
+impl From<String> for StripError {
+    fn from(msg: String) -> Self {
+        Self::Invalid(msg)
+    }
+}
+impl From<&str> for StripError {
+    fn from(msg: &str) -> Self {
+        Self::Invalid(msg.into())
+    }
+}
+
+/// Rewrite a COFF object without the sections whose name matches one of `patterns`.
+///
+/// Returns `Ok(None)` if no sections match or `data` isn't a COFF object, such
+/// as a short import. Static symbols defined in a removed
+/// section, such as the section's own symbol, are kept but no longer refer to
+/// a section. Removing a section fails if an external symbol is defined in it,
+/// if a kept section has relocations against it or if a COMDAT section is
+/// associated with it.
+fn strip_sections(data: &[u8], patterns: &[Pattern]) -> Result<Option<Vec<u8>>, StripError> {
+    if is_bigobj(data) {
+        return Err("stripping sections from bigobj members isn't supported".into());
+    }
+    let Ok(file) = CoffFile::<_, ImageFileHeader>::parse(data) else { return Ok(None) };
+    let header = file.coff_header();
+    let symbols = file.coff_symbol_table();
+    let strings = symbols.strings();
+    let sections = file.coff_section_table();
+
+    // The new number of each section, or `None` if it's removed.
+    let mut numbers = Vec::with_capacity(sections.len());
+    let mut next = 1u16;
+    for section in sections.iter() {
+        let name = String::from_utf8_lossy(section.name(strings)?);
+        if patterns.iter().any(|pattern| pattern.matches_symbol(&name)) {
+            numbers.push(None);
+        } else {
+            numbers.push(Some(next));
+            next += 1;
+        }
+    }
+    if numbers.iter().all(Option::is_some) {
+        return Ok(None);
+    }
+    // Section numbers that aren't positive are special values that are kept as-is.
+    let new_number = |number: i32| match number {
+        1.. => numbers.get(number as usize - 1).copied().flatten(),
+        _ => Some(number as u16),
+    };
+
+    // Check that nothing that's kept depends on a removed section.
+    for (index, symbol) in symbols.iter() {
+        let removed = new_number(symbol.section_number()).is_none();
+        let storage_class = symbol.storage_class();
+        if removed
+            && (storage_class == pe::IMAGE_SYM_CLASS_EXTERNAL
+                || storage_class == pe::IMAGE_SYM_CLASS_WEAK_EXTERNAL)
+        {
+            let name = String::from_utf8_lossy(symbol.name(strings).unwrap_or_default());
+            return Err(
+                format!("the external symbol {name} is defined in a removed section").into()
+            );
+        }
+        if !removed
+            && symbol.has_aux_section()
+            && let Ok(aux) = symbols.aux_section(index)
+            && aux.selection == pe::IMAGE_COMDAT_SELECT_ASSOCIATIVE
+            && new_number(aux.number.get(LE).into()).is_none()
+        {
+            return Err("a COMDAT section is associated with a removed section".into());
+        }
+    }
+    for (index, section) in sections.iter().enumerate() {
+        if numbers[index].is_none() {
+            continue;
+        }
+        for relocation in section.coff_relocations(data)? {
+            let symbol = symbols.symbol(relocation.symbol())?;
+            if new_number(symbol.section_number()).is_none() {
+                return Err("a kept section has relocations against a removed section".into());
+            }
+        }
+    }
+
+    // Write the headers, then the data and relocations of each kept section,
+    // then the symbol and string tables.
+    let optional_header_size = header.size_of_optional_header.get(LE) as usize;
+    let headers_size = 20 + optional_header_size + 40 * (next as usize - 1);
+    let mut out = vec![0; headers_size];
+    let file_header =
+        data.get(..20 + optional_header_size).ok_or("the optional header is truncated")?;
+    out[..file_header.len()].copy_from_slice(file_header);
+    out[2..4].copy_from_slice(&(next - 1).to_le_bytes());
+    let mut section_header = 20 + optional_header_size;
+    for (index, section) in sections.iter().enumerate() {
+        if numbers[index].is_none() {
+            continue;
+        }
+        let mut raw = *section;
+        raw.pointer_to_raw_data.set(LE, 0);
+        raw.pointer_to_relocations.set(LE, 0);
+        if let Some((_, size)) = section.coff_file_range()
+            && size > 0
+        {
+            raw.pointer_to_raw_data.set(LE, out.len() as u32);
+            let section_data =
+                section.coff_data(data).map_err(|()| "the data of a section is truncated")?;
+            out.extend_from_slice(section_data);
+        }
+        let relocations = section.coff_relocations(data)?;
+        if !relocations.is_empty() {
+            // An overflowed count is stored in a leading relocation that isn't returned.
+            let leading =
+                usize::from(section.characteristics.get(LE) & pe::IMAGE_SCN_LNK_NRELOC_OVFL != 0);
+            let start = section.pointer_to_relocations.get(LE) as usize;
+            let len = (relocations.len() + leading) * 10;
+            raw.pointer_to_relocations.set(LE, out.len() as u32);
+            let relocations = start
+                .checked_add(len)
+                .and_then(|end| data.get(start..end))
+                .ok_or("the relocations of a section are truncated")?;
+            out.extend_from_slice(relocations);
+        }
+        // Line numbers are deprecated and would otherwise need to be moved as well.
+        raw.pointer_to_linenumbers.set(LE, 0);
+        raw.number_of_linenumbers.set(LE, 0);
+        out[section_header..section_header + 40].copy_from_slice(object::bytes_of(&raw));
+        section_header += 40;
+    }
+
+    let symbol_table = header.pointer_to_symbol_table.get(LE) as usize;
+    if symbol_table != 0 {
+        let symbol_count = header.number_of_symbols.get(LE) as usize;
+        let string_table = symbol_table + symbol_count * 18;
+        let strings_size = data
+            .get(string_table..string_table + 4)
+            .map_or(0, |size| u32::from_le_bytes(size.try_into().unwrap()) as usize);
+        let tables = data
+            .get(symbol_table..string_table + strings_size)
+            .ok_or("the symbol table is truncated")?;
+        let new_symbol_table = out.len();
+        out[8..12].copy_from_slice(&(new_symbol_table as u32).to_le_bytes());
+        out.extend_from_slice(tables);
+
+        for (index, symbol) in symbols.iter() {
+            let record = new_symbol_table + index.0 * 18;
+            let number = new_number(symbol.section_number()).unwrap_or(pe::IMAGE_SYM_DEBUG as u16);
+            out[record + 12..record + 14].copy_from_slice(&number.to_le_bytes());
+            // The section definition refers to the associated section of an
+            // associative COMDAT and, for some compilers, to its own section otherwise.
+            if symbol.has_aux_section()
+                && let Ok(aux) = symbols.aux_section(index)
+                && aux.number.get(LE) != 0
+            {
+                let number = new_number(aux.number.get(LE).into()).unwrap_or(0);
+                out[record + 18 + 12..record + 18 + 14].copy_from_slice(&number.to_le_bytes());
+            }
+        }
+    }
+    Ok(Some(out))
+}
+
+/// Which of a member's header fields were blank in the lib it came from.
+#[derive(Clone, Copy, Default)]
+struct BlankFields {
+    mtime: bool,
+    uid: bool,
+    gid: bool,
+    mode: bool,
+}
+impl BlankFields {
+    fn of(member: &ArchiveMember<'_>) -> Self {
+        Self {
+            mtime: member.date().is_none(),
+            uid: member.uid().is_none(),
+            gid: member.gid().is_none(),
+            mode: member.mode().is_none(),
+        }
+    }
+}
+
+/// Blank the header fields of the members of a newly written COFF or GNU archive.
+///
+/// `ar_archive_writer` always writes a number so this overwrites the fields with
+/// spaces afterwards. `blank` has an entry for each member of the archive, in order.
+fn blank_header_fields(archive: &mut [u8], blank: &[BlankFields]) -> Result<(), WinlibError> {
+    let parse_error =
+        |e| WinlibError::ObjectError { msg: "could not read new library file".into(), cause: e };
+    let mut offsets = Vec::with_capacity(blank.len());
+    for member in ArchiveFile::parse(&*archive).map_err(parse_error)?.members() {
+        offsets.push(member.map_err(parse_error)?.file_range().0);
+    }
+    for (offset, blank) in offsets.into_iter().zip(blank) {
+        // The member header is 60 bytes and, in these formats, immediately precedes the data.
+        let header = offset as usize - 60;
+        let fields =
+            [(blank.mtime, 16..28), (blank.uid, 28..34), (blank.gid, 34..40), (blank.mode, 40..48)];
+        for (_, range) in fields.into_iter().filter(|(blank, _)| *blank) {
+            archive[header + range.start..header + range.end].fill(b' ');
+        }
+    }
+    Ok(())
+}
+
+/// Fail if `member` is larger than `max_size`, before its data is used.
+fn check_member_size(
+    lib: &OsStr,
+    member: &ArchiveMember,
+    max_size: Option<u64>,
+) -> Result<(), WinlibError> {
+    let (offset, size) = member.file_range();
+    match max_size {
+        Some(max_size) if size > max_size => Err(WinlibError::InvalidInput {
+            msg: format!(
+                "the member {} at {offset:#x} in {} is {size:#X} bytes, more than the maximum of {max_size:#X} bytes",
+                String::from_utf8_lossy(member.name()),
+                lib.display()
+            ),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn report_duplicates(duplicates: usize) {
+    if is_quiet() {
+        return;
+    }
+    let plural = if duplicates == 1 { "" } else { "s" };
+    eprintln!("removed {duplicates} duplicate member{plural}");
+}
+
+fn report_empty(empty: usize) {
+    if is_quiet() {
+        return;
+    }
+    let plural = if empty == 1 { "" } else { "s" };
+    eprintln!("excluded {empty} member{plural} without any symbols");
+}
+
+/// Create a lib at `out_lib` from the members of `from_lib` that aren't excluded by `options`.
+pub fn create_lib(
+    from_lib: &OsStr,
+    out_lib: &OsStr,
+    options: &CreateOptions,
+) -> Result<(), WinlibError> {
+    create_lib_from(&[from_lib.to_owned()], out_lib, options)
+}
+
+/// Expand a path whose file name contains `*` or `?` to the matching files in
+/// its directory, sorted by name. Other paths are returned as they are.
+fn expand_lib_glob(path: &OsStr) -> Result<Vec<OsString>, WinlibError> {
+    let Some(pattern) = Path::new(path)
+        .file_name()
+        .and_then(OsStr::to_str)
+        .filter(|name| name.contains(['*', '?']))
+    else {
+        return Ok(vec![path.to_owned()]);
+    };
+    let parent = Path::new(path).parent().unwrap_or(Path::new(""));
+    let dir = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+    let error =
+        |e| WinlibError::IoError { msg: format!("cannot read {}", dir.display()), cause: e };
+    let mut libs = Vec::new();
+    for entry in fs::read_dir(dir).map_err(error)? {
+        let entry = entry.map_err(error)?;
+        if let Some(name) = entry.file_name().to_str()
+            && glob_match(pattern, name)
+            && entry.file_type().map_err(error)?.is_file()
+        {
+            libs.push(parent.join(name).into_os_string());
+        }
+    }
+    if libs.is_empty() {
+        return Err(WinlibError::InvalidInput {
+            msg: format!("no files match {}", path.display()),
+        });
+    }
+    libs.sort();
+    Ok(libs)
+}
+
+/// Create a lib at `out_lib` from the members of all `from_libs` that aren't
+/// excluded by `options`.
+///
+/// File names containing `*` or `?` are expanded to the matching libs. The
+/// offsets in `options.exclude_offsets` apply to every lib. A member with the
+/// same name as a member included from an earlier lib is excluded with a
+/// warning, unless it's an import or import object.
+pub fn create_lib_from(
+    from_libs: &[OsString],
+    out_lib: &OsStr,
+    options: &CreateOptions,
+) -> Result<(), WinlibError> {
+    let start = Instant::now();
+    let mut expanded = Vec::new();
+    for lib in from_libs {
+        expanded.extend(expand_lib_glob(lib)?);
+    }
+    let from_libs = expanded;
+    if options.in_place && from_libs.len() != 1 {
+        return Err(WinlibError::InvalidInput {
+            msg: "a lib can only be replaced in place when creating it from one lib".into(),
+        });
+    }
+    let extracted_lib = options.save_excluded.as_deref();
+    let gnu_lib = options.also_gnu.as_deref();
+    let manifest_path = options.output_manifest.as_deref();
+    if options.append && out_lib == "-" {
+        return Err(WinlibError::InvalidInput { msg: "cannot append to stdout".into() });
+    }
+    if options.write_thin {
+        // Thin members are referred to by a path relative to the new lib.
+        let msg = if from_libs.iter().any(|lib| lib == "-") {
+            Some("a thin lib can't refer to members read from stdin")
+        } else if out_lib == "-" {
+            Some("a thin lib can't be written to stdout")
+        } else if options.append {
+            Some("members can't be appended to a thin lib")
+        } else if options.archive_format == Some(ArchiveFormat::Coff) {
+            Some("a thin lib must be written in the GNU format")
+        } else if !options.rename_dlls.is_empty() || !options.strip_sections.is_empty() {
+            Some("a thin lib can't refer to changed members")
+        } else if options.preserve_metadata {
+            Some("a thin lib can't preserve blank header fields")
+        } else {
+            None
+        };
+        if let Some(msg) = msg {
+            return Err(WinlibError::InvalidInput { msg: msg.into() });
+        }
+    }
+    if !options.force && !options.dry_run {
+        // Replacing `out_lib` is the point of writing in place or appending.
+        let out_lib = Some(out_lib).filter(|_| !options.in_place && !options.append);
+        for path in [out_lib, extracted_lib, gnu_lib, manifest_path].into_iter().flatten() {
+            for from_lib in &from_libs {
+                check_overwrite(from_lib, path)?;
+            }
+        }
+    }
+    let readers =
+        from_libs.iter().map(|lib| LibReader::open(lib)).collect::<Result<Vec<_>, _>>()?;
+    // Appending to a lib that doesn't exist yet creates it.
+    let existing = if options.append && !options.dry_run {
+        match LibReader::open(out_lib) {
+            Ok(lib) => Some(lib),
+            Err(WinlibError::IoError { cause, .. }) if cause.kind() == io::ErrorKind::NotFound => {
+                None
+            }
+            Err(e) => return Err(e),
+        }
+    } else {
+        None
+    };
+
+    let mut extracted_members = Vec::new();
+    let mut included_members = Vec::new();
+    let mut dry_run_included = Vec::new();
+    let mut dry_run_excluded = Vec::new();
+    let mut selected = Vec::new();
+    // The number of members excluded for each reason.
+    let mut excluded_by: BTreeMap<ExcludeReason, usize> = BTreeMap::new();
+
+    let mut members = Vec::new();
+    let mut archive_format = options.archive_format;
+    for (source, reader) in readers.iter().enumerate() {
+        archive_format.get_or_insert_with(|| reader.format());
+        for member in reader.members() {
+            let member = member?;
+            check_member_size(reader.path(), &member.member, options.max_member_size)?;
+            let data = member.data()?;
+            members.push((source, member.member, data));
+        }
+    }
+    let archive_format = if options.write_thin {
+        ArchiveFormat::Gnu
+    } else {
+        archive_format.unwrap_or(ArchiveFormat::Coff)
+    };
+    if gnu_lib.is_some() && archive_format != ArchiveFormat::Coff {
+        return Err(WinlibError::InvalidInput {
+            msg: "a GNU copy can only be written alongside a COFF lib".into(),
+        });
+    }
+    // Parsing every member is the slowest part so do it up front on all threads.
+    let mut parse_time = None;
+    let kinds = if options.exclude_idata {
+        let data: Vec<&[u8]> = members.iter().map(|&(_, _, data)| data).collect();
+        let progress =
+            (options.progress && !is_quiet()).then(|| Progress::new("classified", members.len()));
+        let parse_start = Instant::now();
+        let kinds = classify_members(&data, options.jobs, progress.as_ref());
+        parse_time = Some(parse_start.elapsed());
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+        kinds
+    } else {
+        Vec::new()
+    };
+    let stats = Stats {
+        elapsed: Duration::ZERO,
+        members: members.len(),
+        bytes: readers.iter().map(|reader| reader.data.len() as u64).sum(),
+        parse_time,
+    };
+
+    // The name and data of each included member, when removing duplicates.
+    let mut seen = HashSet::new();
+    let mut duplicates = 0;
+    let mut empty = 0;
+    // Whether each of the excluded offsets has matched a member.
+    let mut matched_offsets = vec![false; options.exclude_offsets.len()];
+    // Whether each entry of the member list has matched a member, and the
+    // position in the list of each included member.
+    let mut matched_entries = vec![false; options.member_list.as_ref().map_or(0, Vec::len)];
+    let mut list_positions = Vec::new();
+    // Maps the name of each included member to the index of the lib it came from.
+    let mut sources: HashMap<String, usize> = HashMap::new();
+    let progress =
+        (options.progress && !is_quiet()).then(|| Progress::new("processed", members.len()));
+    for (index, (source, member, data)) in members.into_iter().enumerate() {
+        if let Some(progress) = &progress {
+            progress.tick();
+        }
+        let from_lib: &OsStr = &from_libs[source];
+        let name = String::from_utf8_lossy(member.name());
+        let mut excluded_offset = false;
+        for (range, matched) in options.exclude_offsets.iter().zip(&mut matched_offsets) {
+            if range.contains(&member.file_range().0) {
+                *matched = true;
+                excluded_offset = true;
+            }
+        }
+        // The first entry of the member list that refers to this member.
+        let mut list_position = None;
+        for (position, (entry, matched)) in
+            options.member_list.iter().flatten().zip(&mut matched_entries).enumerate()
+        {
+            let matches = match entry {
+                MemberRef::Name(entry) => *entry == name,
+                MemberRef::Offset(offset) => *offset == member.file_range().0,
+            };
+            if matches {
+                *matched = true;
+                list_position.get_or_insert(position);
+            }
+        }
+        // The first reason the member is excluded, if any.
+        let mut reason = if !options.include_names.is_empty()
+            && !options.include_names.iter().any(|pattern| pattern.matches_name(&name))
+        {
+            Some(ExcludeReason::NotIncluded)
+        } else if options.member_list.is_some() && list_position.is_none() {
+            Some(ExcludeReason::NotListed)
+        } else if excluded_offset {
+            Some(ExcludeReason::Offset)
+        } else if options.exclude_names.iter().any(|pattern| pattern.matches_name(&name)) {
+            Some(ExcludeReason::Name)
+        } else if !options.exclude_symbols.is_empty()
+            && member_symbols(data).into_iter().flatten().any(|symbol| {
+                options.exclude_symbols.iter().any(|p| p.matches_symbol(symbol.name()))
+            })
+        {
+            Some(ExcludeReason::Symbol)
+        } else if !options.exclude_dlls.is_empty()
+            && ImportFile::parse(data).is_ok_and(|file| {
+                options.exclude_dlls.iter().any(|dll| dll.matches_dll(file.dll()))
+            })
+        {
+            Some(ExcludeReason::Dll)
+        } else if options.machine.is_some_and(|machine| match member_machine(data) {
+            Some(pe::IMAGE_FILE_MACHINE_UNKNOWN) => false,
+            Some(member_machine) => !machines_match(member_machine, machine),
+            None => options.drop_unparseable,
+        }) {
+            Some(ExcludeReason::Machine)
+        } else if options.import_kind.is_some_and(|kind| is_other_import_kind(data, kind)) {
+            Some(ExcludeReason::ImportType)
+        } else if !options.exclude_sections.is_empty()
+            && member_sections(data).into_iter().flatten().any(|(section, _)| {
+                options.exclude_sections.iter().any(|prefix| section.starts_with(prefix.as_str()))
+            })
+        {
+            Some(ExcludeReason::Section)
+        } else {
+            None
+        };
+        if reason.is_none() && options.exclude_idata {
+            match kinds[index] {
+                Ok(kind) => {
+                    if matches!(
+                        kind,
+                        MemberKind::Import
+                            | MemberKind::ImportObject
+                            | MemberKind::DelayImportObject
+                    ) {
+                        reason = Some(ExcludeReason::Idata);
+                    }
+                }
+                Err(e) if options.skip_unrecognized => {
+                    warn!(
+                        "keeping unrecognised archive member at {:#x} in {}\ncause: {e}",
+                        member.file_range().0,
+                        from_lib.display()
+                    );
+                }
+                Err(e) => {
+                    return Err(WinlibError::ObjectError {
+                        msg: format!(
+                            "unrecognised archive member at {:#x} in {}",
+                            member.file_range().0,
+                            from_lib.display()
+                        ),
+                        cause: e,
+                    });
+                }
+            }
+        }
+
+        // Short imports always have a symbol and other members are left alone.
+        // Weak externals only provide a default, so they don't count.
+        if reason.is_none()
+            && options.exclude_empty
+            && member_symbols(data).is_some_and(|symbols| {
+                symbols.iter().all(|symbol| matches!(symbol, MemberSymbol::Weak { .. }))
+            })
+        {
+            reason = Some(ExcludeReason::Empty);
+            empty += 1;
+        }
+
+        if reason.is_none() && from_libs.len() > 1 && !is_import_member(data) {
+            let first = *sources.entry(name.to_string()).or_insert(source);
+            if first != source {
+                warn!(
+                    "excluding member {name} at {:#x} in {} because {} already has a member with that name",
+                    member.file_range().0,
+                    from_lib.display(),
+                    from_libs[first].display()
+                );
+                reason = Some(ExcludeReason::SameName);
+            }
+        }
+
+        if options.dry_run {
+            if let Some(reason) = reason {
+                *excluded_by.entry(reason).or_default() += 1;
+            }
+            let selection =
+                if reason.is_some() { &mut dry_run_excluded } else { &mut dry_run_included };
+            selection.push((member.file_range().0, name.into_owned()));
+            continue;
+        }
+
+        let mut new_member = new_archive_member(&member, data);
+        if !options.rename_dlls.is_empty() {
+            let renamed =
+                rename_dll(data, &options.rename_dlls).map_err(|e| WinlibError::InvalidInput {
+                    msg: format!(
+                        "unable to rename the DLL imported by the member at {:#x} in {}: {e}",
+                        member.file_range().0,
+                        from_lib.display()
+                    ),
+                })?;
+            if let Some(renamed) = renamed {
+                new_member.buf = Box::new(renamed);
+            }
+        }
+        if !options.strip_sections.is_empty() {
+            let stripped = strip_sections(data, &options.strip_sections).map_err(|e| {
+                let msg = format!(
+                    "unable to strip sections from the member at {:#x} in {}",
+                    member.file_range().0,
+                    from_lib.display()
+                );
+                match e {
+                    StripError::Object(cause) => WinlibError::ObjectError { msg, cause },
+                    StripError::Invalid(e) => {
+                        WinlibError::InvalidInput { msg: format!("{msg}: {e}") }
+                    }
+                }
+            })?;
+            if let Some(stripped) = stripped {
+                new_member.buf = Box::new(stripped);
+            }
+        }
+        if options.no_symbol_index {
+            new_member.object_reader = &NO_SYMBOLS_READER;
+        }
+        if options.deterministic {
+            new_member.mtime = 0;
+            new_member.uid = 0;
+            new_member.gid = 0;
+            new_member.perms = 0o644;
+        }
+        let blank = if options.preserve_metadata && !options.deterministic {
+            BlankFields::of(&member)
+        } else {
+            BlankFields::default()
+        };
+        if reason.is_some() {
+            if extracted_lib.is_some() {
+                extracted_members.push((new_member, blank));
+            }
+        } else if options.dedupe && !seen.insert((new_member.member_name.clone(), data)) {
+            duplicates += 1;
+            reason = Some(ExcludeReason::Duplicate);
+        } else {
+            if let Some(target) = options.target
+                && let Some(msg) = target_mismatch(from_lib, &member, data, target)
+            {
+                if options.strict {
+                    return Err(WinlibError::InvalidInput { msg });
+                }
+                warn!("{msg}");
+            }
+            if options.write_thin {
+                if !member.is_thin() {
+                    return Err(WinlibError::InvalidInput {
+                        msg: format!(
+                            "{name} is stored in {} rather than in its own file, so a thin lib can't refer to it",
+                            from_lib.display()
+                        ),
+                    });
+                }
+                let out_dir = Path::new(out_lib).parent().unwrap_or(Path::new(""));
+                let out_dir = if out_dir == Path::new("") { Path::new(".") } else { out_dir };
+                let path = relative_path(out_dir, &thin_member_path(from_lib, member.name()));
+                new_member.member_name = path.to_string_lossy().into_owned();
+                // Thin archives conventionally use forward slashes.
+                if cfg!(windows) {
+                    new_member.member_name = new_member.member_name.replace('\\', "/");
+                }
+            }
+            included_members.push((new_member, blank));
+            list_positions.push(list_position);
+        }
+        if let Some(reason) = reason {
+            *excluded_by.entry(reason).or_default() += 1;
+        }
+        if manifest_path.is_some() {
+            selected.push((source, member.file_range().0, name.into_owned(), reason));
+        }
+    }
+    if options.dedupe {
+        report_duplicates(duplicates);
+    }
+    if options.exclude_empty {
+        report_empty(empty);
+    }
+
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+
+    let unmatched: Vec<_> = options
+        .exclude_offsets
+        .iter()
+        .zip(&matched_offsets)
+        .filter(|(_, matched)| !**matched)
+        .map(|(range, _)| match (range.start(), range.end()) {
+            (start, end) if start == end => format!("{start:#X}"),
+            (start, end) => format!("{start:#X}-{end:#X}"),
+        })
+        .collect();
+    if !unmatched.is_empty() {
+        let msg = format!(
+            "no members at the excluded offsets {} in {}",
+            unmatched.join(", "),
+            from_libs.iter().map(|lib| lib.display().to_string()).collect::<Vec<_>>().join(", ")
+        );
+        if options.strict {
+            return Err(WinlibError::InvalidInput { msg });
+        }
+        warn!("{msg}");
+    }
+
+    let unlisted: Vec<_> = options
+        .member_list
+        .iter()
+        .flatten()
+        .zip(&matched_entries)
+        .filter(|(_, matched)| !**matched)
+        .map(|(entry, _)| entry.to_string())
+        .collect();
+    if !unlisted.is_empty() {
+        return Err(WinlibError::InvalidInput {
+            msg: format!(
+                "no members matching {} in {}",
+                unlisted.join(", "),
+                from_libs
+                    .iter()
+                    .map(|lib| lib.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        });
+    }
+    if options.member_list.is_some() {
+        // The sort is stable so members matching the same entry stay in the lib's order.
+        let mut positioned: Vec<_> = list_positions.into_iter().zip(included_members).collect();
+        positioned.sort_by_key(|(position, _)| *position);
+        included_members = positioned.into_iter().map(|(_, member)| member).collect();
+    }
+
+    // Appending keeps the existing members so only a new lib can end up empty.
+    let included = if options.dry_run { dry_run_included.len() } else { included_members.len() };
+    if included == 0 && !options.append {
+        let mut msg = format!("the new lib {} has no members", out_lib.display());
+        let mut reasons: Vec<_> = excluded_by.into_iter().collect();
+        reasons.sort_by_key(|&(_, count)| Reverse(count));
+        let reasons: Vec<_> = reasons
+            .into_iter()
+            .map(|(reason, count)| {
+                let plural = if count == 1 { "" } else { "s" };
+                format!("{count} member{plural} {}", reason.description())
+            })
+            .collect();
+        if reasons.is_empty() && from_libs.len() == 1 {
+            msg.push_str("\nhelp: the old lib has no members");
+        } else if reasons.is_empty() {
+            msg.push_str("\nhelp: the old libs have no members");
+        } else {
+            msg.push_str(&format!("\nhelp: {}", reasons.join(", ")));
+        }
+        if options.strict {
+            return Err(WinlibError::InvalidInput { msg });
+        }
+        warn!("{msg}");
+    }
+
+    if options.dry_run && is_quiet() {
+        return Ok(());
+    } else if options.dry_run {
+        let mut out = buffered_stdout();
+        for (heading, members) in [("INCLUDE", dry_run_included), ("EXCLUDE", dry_run_excluded)] {
+            writeln!(out, "{heading}:").map_err(stdout_error)?;
+            for (offset, name) in members {
+                writeln!(out, "{offset:>#10X}  {name}").map_err(stdout_error)?;
+            }
+        }
+        return out.flush().map_err(stdout_error);
+    }
+
+    if let Some(existing) = &existing {
+        let names: HashSet<_> =
+            included_members.iter().map(|(member, _)| member.member_name.clone()).collect();
+        let mut existing_members = Vec::new();
+        for member in existing.members() {
+            let member = member?;
+            let data = member.data()?;
+            let mut new_member = new_archive_member(&member.member, data);
+            if !options.allow_duplicates && names.contains(&new_member.member_name) {
+                return Err(WinlibError::InvalidInput {
+                    msg: format!(
+                        "{} already has a member named {}\nhelp: use --allow-duplicates to keep both",
+                        out_lib.display(),
+                        new_member.member_name
+                    ),
+                });
+            }
+            // The whole lib is rewritten so treat its members like the new ones.
+            if options.no_symbol_index {
+                new_member.object_reader = &NO_SYMBOLS_READER;
+            }
+            if options.deterministic {
+                new_member.mtime = 0;
+                new_member.uid = 0;
+                new_member.gid = 0;
+                new_member.perms = 0o644;
+            }
+            let blank = if options.preserve_metadata && !options.deterministic {
+                BlankFields::of(&member.member)
+            } else {
+                BlankFields::default()
+            };
+            existing_members.push((new_member, blank));
+        }
+        included_members.splice(0..0, existing_members);
+    }
+
+    if options.sort {
+        // The sort is stable so members with the same name stay in their original order.
+        included_members.sort_by(|(a, _), (b, _)| a.member_name.cmp(&b.member_name));
+        extracted_members.sort_by(|(a, _), (b, _)| a.member_name.cmp(&b.member_name));
+    }
+    let (included_members, included_blank): (Vec<_>, Vec<_>) = included_members.into_iter().unzip();
+    let (extracted_members, extracted_blank): (Vec<_>, Vec<_>) =
+        extracted_members.into_iter().unzip();
+    let manifest = manifest_path
+        .map(|path| (path, create_manifest(&from_libs, out_lib, &selected, &included_members)));
+
+    let write_archive = |members: &[NewArchiveMember<'_>],
+                         kind: ar_archive_writer::ArchiveKind,
+                         thin: bool,
+                         blank: &[BlankFields]| {
+        let mut writer = Cursor::new(Vec::with_capacity(64 * 1024));
+        let is_ec = needs_ec_symbols(members, kind);
+        ar_archive_writer::write_archive_to_stream(&mut writer, members, kind, thin, is_ec)
+            .map_err(|e| WinlibError::IoError {
+                msg: "could not create new library file".into(),
+                cause: e,
+            })?;
+        if options.preserve_metadata {
+            blank_header_fields(writer.get_mut(), blank)?;
+        }
+        Ok::<_, WinlibError>(writer.into_inner())
+    };
+    // The excluded members get the same symbol index treatment as the
+    // included members so either lib can be used on its own.
+    let extracted = match extracted_lib {
+        Some(lib) => Some((
+            lib,
+            write_archive(&extracted_members, archive_format.kind(), false, &extracted_blank)?,
+        )),
+        None => None,
+    };
+    let gnu = match gnu_lib {
+        Some(lib) => Some((
+            lib,
+            write_archive(
+                &included_members,
+                ar_archive_writer::ArchiveKind::Gnu,
+                false,
+                &included_blank,
+            )?,
+        )),
+        None => None,
+    };
+    let new_lib = write_archive(
+        &included_members,
+        archive_format.kind(),
+        options.write_thin,
+        &included_blank,
+    )?;
+    // Any of the outputs can be one of the inputs, which has to be closed
+    // before it can be replaced on Windows.
+    drop(included_members);
+    drop(extracted_members);
+    drop(readers);
+    drop(existing);
+    for (lib, data) in extracted.iter().chain(&gnu) {
+        write_lib(lib, data)?;
+    }
+    write_lib(out_lib, &new_lib)?;
+    // The manifest is only written once the lib it describes exists.
+    if let Some((path, manifest)) = manifest {
+        fs::write(path, manifest).map_err(|e| WinlibError::IoError {
+            msg: format!("unable to write the manifest to {}", path.display()),
+            cause: e,
+        })?;
+    }
+    if options.stats {
+        Stats { elapsed: start.elapsed(), ..stats }.print();
+    }
+
+    Ok(())
+}
+
+/// Create a lib at `out_lib` containing the members of all `libs`.
+///
+/// Fails if two libs contain a member with the same name, unless
+/// `options.allow_duplicates` is set, or if `out_lib` exists, unless
+/// `options.force` is set.
+pub fn merge_libs(
+    libs: &[OsString],
+    out_lib: &OsStr,
+    options: &MergeOptions,
+) -> Result<(), WinlibError> {
+    if !options.force {
+        for lib in libs {
+            check_overwrite(lib, out_lib)?;
+        }
+    }
+    let readers = libs.iter().map(|lib| LibReader::open(lib)).collect::<Result<Vec<_>, _>>()?;
+
+    let mut members = Vec::new();
+    // Maps each member name to the index of the first lib that contains it.
+    let mut names: HashMap<String, usize> = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut duplicates = 0;
+    for (index, reader) in readers.iter().enumerate() {
+        for member in reader.members() {
+            let member = member?;
+            let member_data = member.data()?;
+            let new_member = new_archive_member(&member.member, member_data);
+            if options.dedupe && !seen.insert((new_member.member_name.clone(), member_data)) {
+                duplicates += 1;
+                continue;
+            }
+            let first = if is_import_member(member_data) {
+                index
+            } else {
+                *names.entry(new_member.member_name.clone()).or_insert(index)
+            };
+            if first != index && !options.allow_duplicates {
+                return Err(WinlibError::InvalidInput {
+                    msg: format!(
+                        "duplicate member {} in {} and {}\nhelp: use --allow-duplicates to keep both",
+                        new_member.member_name,
+                        libs[first].display(),
+                        reader.path().display()
+                    ),
+                });
+            }
+            members.push(new_member);
+        }
+    }
+
+    if options.dedupe {
+        report_duplicates(duplicates);
+    }
+    if options.sort {
+        members.sort_by(|a, b| a.member_name.cmp(&b.member_name));
+    }
+
+    let mut writer = Cursor::new(Vec::with_capacity(64 * 1024));
+    let format = options.archive_format.or_else(|| readers.first().map(LibReader::format));
+    let kind = format.unwrap_or(ArchiveFormat::Coff).kind();
+    ar_archive_writer::write_archive_to_stream(
+        &mut writer,
+        &members,
+        kind,
+        false,
+        needs_ec_symbols(&members, kind),
+    )
+    .map_err(|e| WinlibError::IoError {
+        msg: "could not create new library file".into(),
+        cause: e,
+    })?;
+    // Release the inputs in case one of them is being overwritten.
+    drop(members);
+    drop(readers);
+    write_lib(out_lib, writer.get_ref())?;
+
+    Ok(())
+}
+
+/// Add the object files `objects` to the end of the lib at `lib_path`.
+///
+/// Unless `mixed` is set, each object must have the same machine type as the
+/// members already in the lib. Each object is named after its file name, or
+/// `member_name` if it's given, in which case there must be only one object.
+/// Unless `allow_duplicates` is set, that name must not already be the name of
+/// a member.
+pub fn add_objects(
+    lib_path: &OsStr,
+    objects: &[OsString],
+    member_name: Option<&str>,
+    mixed: bool,
+    allow_duplicates: bool,
+) -> Result<(), WinlibError> {
+    if member_name.is_some() && objects.len() != 1 {
+        return Err(WinlibError::InvalidInput {
+            msg: format!("--as names a single object but {} objects were given", objects.len()),
+        });
+    } else if member_name == Some("") {
+        return Err(WinlibError::InvalidInput {
+            msg: "the member name given by --as is empty".into(),
+        });
+    }
+    let lib = LibReader::open(lib_path)?;
+    let objects_data =
+        objects.iter().map(|object| read_file(object)).collect::<Result<Vec<_>, _>>()?;
+
+    let mut members = Vec::new();
+    let mut machines = BTreeMap::new();
+    for member in lib.members() {
+        let member = member?;
+        let member_data = member.data()?;
+        if let Some(machine) = member_machine(member_data)
+            && machine != pe::IMAGE_FILE_MACHINE_UNKNOWN
+        {
+            machines.entry(machine).or_insert_with(|| member.name());
+        }
+        members.push(new_archive_member(&member.member, member_data));
+    }
+
+    for (path, object_data) in objects.iter().zip(&objects_data) {
+        let Some(machine) = member_machine(object_data) else {
+            return Err(WinlibError::InvalidInput {
+                msg: format!("{} is not a COFF object", path.display()),
+            });
+        };
+        if !mixed
+            && machine != pe::IMAGE_FILE_MACHINE_UNKNOWN
+            && let Some((&other, name)) =
+                machines.iter().find(|&(&other, _)| !machines_match(other, machine))
+        {
+            return Err(WinlibError::InvalidInput {
+                msg: format!(
+                    "{} is for {} but the member {name} in {} is for {}\nhelp: use --mixed to add it anyway",
+                    path.display(),
+                    machine_name(machine),
+                    lib_path.display(),
+                    machine_name(other),
+                ),
+            });
+        }
+        let name = match member_name {
+            Some(name) => name.to_owned(),
+            None => Path::new(path).file_name().unwrap_or(path).to_string_lossy().into_owned(),
+        };
+        if !allow_duplicates && members.iter().any(|member| member.member_name == name) {
+            return Err(WinlibError::InvalidInput {
+                msg: format!(
+                    "{} already contains a member named {name}\nhelp: use --allow-duplicates to add it anyway",
+                    lib_path.display()
+                ),
+            });
+        }
+        members.push(NewArchiveMember::new(&**object_data, &COFF_OBJECT_READER, name));
+    }
+
+    let mut writer = Cursor::new(Vec::with_capacity(64 * 1024));
+    let kind = lib.format().kind();
+    ar_archive_writer::write_archive_to_stream(
+        &mut writer,
+        &members,
+        kind,
+        false,
+        needs_ec_symbols(&members, kind),
+    )
+    .map_err(|e| WinlibError::IoError {
+        msg: "could not create new library file".into(),
+        cause: e,
+    })?;
+    // Release the input before overwriting it.
+    drop(members);
+    drop(lib);
+    write_lib(lib_path, writer.get_ref())?;
+
+    Ok(())
+}
+
+/// Find the offset of the header of every member of a COFF, GNU or BSD archive,
+/// including the symbol index and long name table.
+///
+/// Returns the offset of the first header that can't be read if there is one.
+fn member_headers(data: &[u8]) -> Result<Vec<usize>, usize> {
+    let mut headers = Vec::new();
+    let mut offset = 8;
+    while offset < data.len() {
+        let size = data
+            .get(offset..offset + 60)
+            .and_then(|header| std::str::from_utf8(&header[48..58]).ok())
+            .and_then(|size| size.trim_end().parse::<usize>().ok())
+            .ok_or(offset)?;
+        headers.push(offset);
+        offset = (offset + 60)
+            .checked_add(size)
+            .and_then(|end| end.checked_add(size % 2))
+            .ok_or(offset)?;
+    }
+    Ok(headers)
+}
+
+/// Write a copy of the lib at `lib_path` to `out_lib` with the modification time
+/// of every member set to `mtime`.
+///
+/// Everything else is copied unchanged, including blank modification times.
+/// Fails if `out_lib` exists, unless `force` is set.
+pub fn touch_lib(
+    lib_path: &OsStr,
+    out_lib: &OsStr,
+    mtime: u64,
+    force: bool,
+) -> Result<(), WinlibError> {
+    let date = mtime.to_string();
+    if date.len() > 12 {
+        return Err(WinlibError::InvalidInput {
+            msg: format!("the modification time {mtime} doesn't fit in a member header"),
+        });
+    }
+    if !force {
+        check_overwrite(lib_path, out_lib)?;
+    }
+    let data = read_file(lib_path)?;
+    parse_archive(&data, lib_path)?;
+    if !data.starts_with(b"!<arch>\n") {
+        return Err(WinlibError::InvalidInput {
+            msg: format!("{} isn't a COFF, GNU or BSD archive", lib_path.display()),
+        });
+    }
+    let headers = member_headers(&data).map_err(|offset| WinlibError::InvalidInput {
+        msg: format!("could not read the member header at {offset:#x} in {}", lib_path.display()),
+    })?;
+
+    let mut new_data = data.to_vec();
+    for header in headers {
+        let field = &mut new_data[header + 16..header + 28];
+        if field.iter().all(|&b| b == b' ') {
+            continue;
+        }
+        field.fill(b' ');
+        field[..date.len()].copy_from_slice(date.as_bytes());
+    }
+    // Release the input in case it's being overwritten.
+    drop(data);
+    write_lib(out_lib, &new_data)
+}
+
+/// A symbol provided by an archive member.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MemberSymbol {
+    /// An external symbol defined by a COFF object.
+    Defined(String),
+    /// A weak external defined by a COFF object, which is an alias for
+    /// `target` unless another member defines `name`.
+    Weak { name: String, target: String },
+    /// A symbol imported from a DLL by a short import member.
+    ///
+    /// `ordinal` is set if the symbol is imported by ordinal rather than by name.
+    Import { name: String, dll: String, ordinal: Option<u16> },
+}
+impl MemberSymbol {
+    /// The name of the symbol.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Defined(name) | Self::Weak { name, .. } | Self::Import { name, .. } => name,
+        }
+    }
+
+    /// The ordinal of a symbol that's imported by ordinal.
+    pub fn ordinal(&self) -> Option<u16> {
+        match self {
+            Self::Import { ordinal, .. } => *ordinal,
+            Self::Defined(_) | Self::Weak { .. } => None,
+        }
+    }
+}
+
+/// Get the symbols provided by an archive member.
+///
+/// Returns `None` if the member is neither a COFF object nor a short import.
+fn member_symbols(data: &[u8]) -> Option<Vec<MemberSymbol>> {
+    if is_bigobj(data) {
+        Some(coff_defined_symbols(&CoffBigFile::<&[u8]>::parse(data).ok()?))
+    } else if let Ok(file) = CoffFile::<_, ImageFileHeader>::parse(data) {
+        Some(coff_defined_symbols(&file))
+    } else if let Ok(file) = ImportFile::parse(data) {
+        Some(vec![MemberSymbol::Import {
+            name: String::from_utf8_lossy(file.symbol()).into(),
+            dll: String::from_utf8_lossy(file.dll()).into(),
+            ordinal: match file.import() {
+                ImportName::Ordinal(ordinal) => Some(ordinal),
+                ImportName::Name(_) => None,
+            },
+        }])
+    } else {
+        None
+    }
+}
+
+fn coff_defined_symbols<'data, C: CoffHeader>(
+    file: &CoffFile<'data, &'data [u8], C>,
+) -> Vec<MemberSymbol> {
+    let symbols = file.coff_symbol_table();
+    let strings = symbols.strings();
+    let mut defined = Vec::new();
+    for (index, symbol) in symbols.iter() {
+        let is_defined = match symbol.storage_class() {
+            pe::IMAGE_SYM_CLASS_EXTERNAL => symbol.section_number() != pe::IMAGE_SYM_UNDEFINED,
+            pe::IMAGE_SYM_CLASS_WEAK_EXTERNAL => true,
+            _ => false,
+        };
+        if !is_defined {
+            continue;
+        }
+        let Ok(name) = symbol.name(strings) else { continue };
+        let name = String::from_utf8_lossy(name).into_owned();
+        // The auxiliary record of a weak external gives the index of the symbol it aliases.
+        let target = (symbol.storage_class() == pe::IMAGE_SYM_CLASS_WEAK_EXTERNAL
+            && symbol.number_of_aux_symbols() > 0)
+            .then(|| {
+                let aux = symbols.aux_weak_external(index).ok()?;
+                let target = symbols.symbol(aux.default_symbol()).ok()?;
+                Some(String::from_utf8_lossy(target.name(strings).ok()?).into_owned())
+            })
+            .flatten();
+        defined.push(match target {
+            Some(target) => MemberSymbol::Weak { name, target },
+            None => MemberSymbol::Defined(name),
+        });
+    }
+    defined
+}
+
+/// Get the name and raw data size of each section in a COFF object.
+///
+/// Returns `None` if the member is not a COFF object.
+fn member_sections(data: &[u8]) -> Option<Vec<(String, u32)>> {
+    if is_bigobj(data) {
+        Some(coff_sections(&CoffBigFile::<&[u8]>::parse(data).ok()?))
+    } else {
+        Some(coff_sections(&CoffFile::<_, ImageFileHeader>::parse(data).ok()?))
+    }
+}
+
+fn coff_sections<'data, C: CoffHeader>(
+    file: &CoffFile<'data, &'data [u8], C>,
+) -> Vec<(String, u32)> {
+    let strings = file.coff_symbol_table().strings();
+    let mut sections = Vec::new();
+    for section in file.coff_section_table().iter() {
+        // Show the raw name if it can't be resolved against the string table.
+        let name = section.name(strings).unwrap_or(section.raw_name());
+        sections.push((String::from_utf8_lossy(name).into(), section.size_of_raw_data.get(LE)));
+    }
+    sections
+}
+
+/// Get the external symbols that a COFF object references but doesn't define.
+///
+/// Returns `None` if the member is not a COFF object.
+fn member_undefined_symbols(data: &[u8]) -> Option<Vec<String>> {
+    if is_bigobj(data) {
+        Some(coff_undefined_symbols(&CoffBigFile::<&[u8]>::parse(data).ok()?))
+    } else {
+        Some(coff_undefined_symbols(&CoffFile::<_, ImageFileHeader>::parse(data).ok()?))
+    }
+}
+
+fn coff_undefined_symbols<'data, C: CoffHeader>(
+    file: &CoffFile<'data, &'data [u8], C>,
+) -> Vec<String> {
+    let symbols = file.coff_symbol_table();
+    let strings = symbols.strings();
+    let mut undefined = Vec::new();
+    for (_, symbol) in symbols.iter() {
+        // An undefined external with a non-zero value is a common symbol.
+        if symbol.storage_class() == pe::IMAGE_SYM_CLASS_EXTERNAL
+            && symbol.section_number() == pe::IMAGE_SYM_UNDEFINED
+            && symbol.value() == 0
+            && let Ok(name) = symbol.name(strings)
+        {
+            undefined.push(String::from_utf8_lossy(name).into());
+        }
+    }
+    undefined
+}
+
+/// Get the machine type of an archive member.
+///
+/// Returns `None` if the member is neither a COFF object nor a short import.
+fn member_machine(data: &[u8]) -> Option<u16> {
+    if is_bigobj(data) {
+        Some(CoffBigFile::<&[u8]>::parse(data).ok()?.coff_header().machine())
+    } else if let Ok(file) = CoffFile::<_, ImageFileHeader>::parse(data) {
+        Some(file.coff_header().machine.get(LE))
+    } else if let Ok(header) = pe::ImportObjectHeader::parse(data, &mut 0) {
+        Some(header.machine.get(LE))
+    } else {
+        None
+    }
+}
+
+/// A human readable name for a COFF machine type.
+pub fn machine_name(machine: u16) -> Cow<'static, str> {
+    match machine {
+        pe::IMAGE_FILE_MACHINE_UNKNOWN => "any".into(),
+        pe::IMAGE_FILE_MACHINE_I386 => "i386".into(),
+        pe::IMAGE_FILE_MACHINE_AMD64 => "x86_64".into(),
+        pe::IMAGE_FILE_MACHINE_ARMNT => "arm".into(),
+        pe::IMAGE_FILE_MACHINE_ARM64 => "arm64".into(),
+        pe::IMAGE_FILE_MACHINE_ARM64EC => "arm64ec".into(),
+        pe::IMAGE_FILE_MACHINE_ARM64X => "arm64x".into(),
+        machine => format!("{machine:#06x}").into(),
+    }
+}
+
+/// The COFF machine type for a Windows target triple such as `x86_64-pc-windows-msvc`.
+///
+/// Returns `None` if the triple isn't a Windows target or its architecture isn't known.
+pub fn target_machine(triple: &str) -> Option<u16> {
+    let (arch, rest) = triple.split_once('-')?;
+    if !rest.split('-').any(|part| part == "windows") {
+        return None;
+    }
+    match arch {
+        "x86_64" => Some(pe::IMAGE_FILE_MACHINE_AMD64),
+        "i386" | "i586" | "i686" => Some(pe::IMAGE_FILE_MACHINE_I386),
+        "aarch64" => Some(pe::IMAGE_FILE_MACHINE_ARM64),
+        "arm64ec" => Some(pe::IMAGE_FILE_MACHINE_ARM64EC),
+        "thumbv7a" => Some(pe::IMAGE_FILE_MACHINE_ARMNT),
+        _ => None,
+    }
+}
+
+/// Whether members for the machines `a` and `b` can be used together.
+///
+/// ARM64X members contain both ARM64 and ARM64EC code so they match either.
+fn machines_match(a: u16, b: u16) -> bool {
+    use pe::IMAGE_FILE_MACHINE_ARM64X as ARM64X;
+    use pe::{IMAGE_FILE_MACHINE_ARM64 as ARM64, IMAGE_FILE_MACHINE_ARM64EC as ARM64EC};
+    a == b || matches!((a, b), (ARM64X, ARM64 | ARM64EC) | (ARM64 | ARM64EC, ARM64X))
+}
+
+/// Describe why the `member` of `lib` doesn't match the `target` machine.
+///
+/// Returns `None` if it matches or isn't specific to a machine.
+fn target_mismatch(
+    lib: &OsStr,
+    member: &ArchiveMember<'_>,
+    data: &[u8],
+    target: u16,
+) -> Option<String> {
+    let machine = member_machine(data)?;
+    (machine != pe::IMAGE_FILE_MACHINE_UNKNOWN && !machines_match(machine, target)).then(|| {
+        format!(
+            "member {} at {:#x} in {} is for {} but the target is {}",
+            String::from_utf8_lossy(member.name()),
+            member.file_range().0,
+            lib.display(),
+            machine_name(machine),
+            machine_name(target)
+        )
+    })
+}
+
+/// Format a Unix timestamp as an ISO-8601 UTC date and time.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let time = secs % 86400;
+    // Convert days since the Unix epoch to a civil date.
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let (hour, min, sec) = (time / 3600, time / 60 % 60, time % 60);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{min:02}:{sec:02}Z")
+}
+
+/// Collect the symbols provided by every member of a lib.
+///
+/// Each symbol is mapped to the DLL it's imported from, if any.
+/// Members that are neither COFF objects nor short imports are ignored.
+fn lib_symbols(lib_path: &OsStr) -> Result<BTreeMap<String, Option<String>>, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+
+    let mut symbols = BTreeMap::new();
+    for member in lib.members() {
+        let member = member?;
+        let data = member.data()?;
+        for symbol in member_symbols(data).into_iter().flatten() {
+            match symbol {
+                MemberSymbol::Defined(name) | MemberSymbol::Weak { name, .. } => {
+                    symbols.insert(name, None)
+                }
+                MemberSymbol::Import { name, dll, .. } => symbols.insert(name, Some(dll)),
+            };
+        }
+    }
+    Ok(symbols)
+}
+
+/// A symbol that differs between two libs, as found by [`diff_libs`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SymbolChange {
+    /// The symbol is only in the old lib.
+    Removed(String),
+    /// The symbol is only in the new lib.
+    Added(String),
+    /// The symbol is imported from a different DLL. A DLL of `None` means the
+    /// symbol isn't an import in that lib.
+    DllChanged { name: String, old_dll: Option<String>, new_dll: Option<String> },
+}
+
+/// Find the symbols that were added, removed or changed between two libs.
+///
+/// Changes to the DLL a symbol is imported from are only included if
+/// `compare_dlls` is set. Removed and changed symbols come first, sorted by
+/// name, followed by the added symbols.
+pub fn diff_libs(
+    old_lib: &OsStr,
+    new_lib: &OsStr,
+    compare_dlls: bool,
+) -> Result<Vec<SymbolChange>, WinlibError> {
+    let old = lib_symbols(old_lib)?;
+    let mut new = lib_symbols(new_lib)?;
+
+    let mut changes = Vec::new();
+    for (name, old_dll) in old {
+        match new.remove(&name) {
+            None => changes.push(SymbolChange::Removed(name)),
+            Some(new_dll) if compare_dlls && old_dll != new_dll => {
+                changes.push(SymbolChange::DllChanged { name, old_dll, new_dll })
+            }
+            Some(_) => {}
+        }
+    }
+    changes.extend(new.into_keys().map(SymbolChange::Added));
+    Ok(changes)
+}
+
+/// The symbols provided by a member of a lib, as returned by [`symbols_by_member`].
+#[derive(Clone, Debug)]
+pub struct MemberSymbols {
+    pub name: String,
+    pub kind: MemberKind,
+    pub symbols: Vec<MemberSymbol>,
+}
+
+/// Read the symbols provided by each member of a lib.
+///
+/// Members that aren't COFF objects or short imports have no symbols.
+pub fn symbols_by_member(lib_path: &OsStr) -> Result<Vec<MemberSymbols>, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+
+    let mut members = Vec::new();
+    for member in lib.members() {
+        let member = member?;
+        let data = member.data()?;
+        members.push(MemberSymbols {
+            name: member.name().into_owned(),
+            kind: classify(data),
+            symbols: member_symbols(data).unwrap_or_default(),
+        });
+    }
+    Ok(members)
+}
+
+/// An undefined symbol and the members that reference it, as returned by
+/// [`undefined_symbols`].
+#[derive(Clone, Debug)]
+pub struct SymbolReferences {
+    pub symbol: String,
+    /// The name and kind of each member that references the symbol, in the lib's order.
+    pub members: Vec<(String, MemberKind)>,
+}
+
+/// Find the symbols that members of a lib reference but don't define, sorted by name.
+pub fn undefined_symbols(lib_path: &OsStr) -> Result<Vec<SymbolReferences>, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+
+    // Maps each undefined symbol to the members that reference it.
+    let mut references: BTreeMap<String, Vec<(String, MemberKind)>> = BTreeMap::new();
+    for member in lib.members() {
+        let member = member?;
+        let data = member.data()?;
+        let name = member.name();
+        let kind = classify(data);
+        for symbol in member_undefined_symbols(data).into_iter().flatten() {
+            let members = references.entry(symbol).or_default();
+            if !members.iter().any(|(member, _)| *member == name) {
+                members.push((name.clone().into_owned(), kind));
+            }
+        }
+    }
+    Ok(references
+        .into_iter()
+        .map(|(symbol, members)| SymbolReferences { symbol, members })
+        .collect())
+}
+
+/// An entry of the import structures in the `.idata$` sections of a COFF object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportEntry {
+    /// An import descriptor, with the DLL name its `Name` field refers to.
+    Descriptor(String),
+    /// The descriptor of zeros that ends the import directory.
+    NullDescriptor,
+    /// An import lookup table entry that imports a symbol by name.
+    ByName { name: String, hint: Option<u16> },
+    /// An import lookup table entry that imports a symbol by ordinal.
+    ByOrdinal(u16),
+    /// A `.idata$7` section holding the DLL name, as written by GNU dlltool.
+    DllName(String),
+}
+impl fmt::Display for ImportEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Descriptor(dll) => write!(f, "import descriptor for {dll}"),
+            Self::NullDescriptor => f.write_str("null import descriptor"),
+            Self::ByName { name, hint: Some(hint) } => write!(f, "{name} by name, hint {hint}"),
+            Self::ByName { name, hint: None } => write!(f, "{name} by name"),
+            Self::ByOrdinal(ordinal) => write!(f, "@{ordinal} by ordinal"),
+            Self::DllName(dll) => write!(f, "DLL name {dll}"),
+        }
+    }
+}
+
+/// What a relocation in an `.idata$` section points to.
+enum ImportTarget<'data> {
+    /// The data from the target onwards, when it's in the same object.
+    Data(&'data [u8]),
+    /// The name of a symbol defined by another member.
+    Symbol(String),
+}
+
+/// Read the import descriptors and import lookup table entries of a COFF object.
+///
+/// Returns `None` if the member is not a COFF object or its `.idata$` sections
+/// can't be read.
+fn member_imports(data: &[u8]) -> Option<Vec<ImportEntry>> {
+    if is_bigobj(data) {
+        coff_imports(&CoffBigFile::<&[u8]>::parse(data).ok()?, data)
+    } else {
+        coff_imports(&CoffFile::<_, ImageFileHeader>::parse(data).ok()?, data)
+    }
+}
+
+fn coff_imports<'data, C: CoffHeader>(
+    file: &CoffFile<'data, &'data [u8], C>,
+    data: &'data [u8],
+) -> Option<Vec<ImportEntry>> {
+    let symbols = file.coff_symbol_table();
+    let strings = symbols.strings();
+    let sections = file.coff_section_table();
+    let thunk_size = match file.coff_header().machine() {
+        pe::IMAGE_FILE_MACHINE_AMD64
+        | pe::IMAGE_FILE_MACHINE_ARM64
+        | pe::IMAGE_FILE_MACHINE_ARM64EC
+        | pe::IMAGE_FILE_MACHINE_ARM64X => 8,
+        _ => 4,
+    };
+    let c_str = |data: &[u8]| {
+        let len = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        String::from_utf8_lossy(&data[..len]).into_owned()
+    };
+    // Objects normally have both an import lookup table and an import address
+    // table, which are identical, so only read the address table if it's alone.
+    let lookup_table =
+        if sections.iter().any(|s| s.name(strings) == Ok(b".idata$4")) { &b"4"[..] } else { b"5" };
+
+    let mut imports = Vec::new();
+    for section in sections.iter() {
+        let Some(kind) = section.name(strings).ok()?.strip_prefix(b".idata$") else { continue };
+        let section_data = section.coff_data(data).ok()?;
+        let relocations = section.coff_relocations(data).ok()?;
+        // COFF relocations store their addend in the data they patch.
+        let target = |offset: usize| -> Option<Option<ImportTarget<'data>>> {
+            let Some(relocation) =
+                relocations.iter().find(|r| r.virtual_address.get(LE) as usize == offset)
+            else {
+                return Some(None);
+            };
+            let symbol = symbols.symbol(relocation.symbol()).ok()?;
+            if symbol.section_number() <= 0 {
+                let name = String::from_utf8_lossy(symbol.name(strings).ok()?);
+                return Some(Some(ImportTarget::Symbol(format!("`{name}`"))));
+            }
+            let addend = u32::from_le_bytes(section_data.get(offset..offset + 4)?.try_into().ok()?);
+            let section = sections.section(object::SectionIndex(symbol.section_number() as usize));
+            let target_data = section.ok()?.coff_data(data).ok()?;
+            let start = usize::try_from(symbol.value().checked_add(addend)?).ok()?;
+            Some(Some(ImportTarget::Data(target_data.get(start..)?)))
+        };
+        match kind {
+            b"2" | b"3" => {
+                for offset in (0..section_data.len() / 20).map(|i| i * 20) {
+                    let descriptor = &section_data[offset..offset + 20];
+                    let relocated = relocations.iter().any(|r| {
+                        (offset..offset + 20).contains(&(r.virtual_address.get(LE) as usize))
+                    });
+                    imports.push(match target(offset + 12)? {
+                        Some(ImportTarget::Data(name)) => ImportEntry::Descriptor(c_str(name)),
+                        Some(ImportTarget::Symbol(name)) => ImportEntry::Descriptor(name),
+                        None if !relocated && descriptor.iter().all(|&b| b == 0) => {
+                            ImportEntry::NullDescriptor
+                        }
+                        None => ImportEntry::Descriptor("an unnamed DLL".into()),
+                    });
+                }
+            }
+            kind if kind == lookup_table => {
+                for offset in (0..section_data.len() / thunk_size).map(|i| i * thunk_size) {
+                    match target(offset)? {
+                        Some(ImportTarget::Data(hint_name)) => imports.push(ImportEntry::ByName {
+                            name: c_str(hint_name.get(2..)?),
+                            hint: Some(u16::from_le_bytes(hint_name.get(..2)?.try_into().ok()?)),
+                        }),
+                        Some(ImportTarget::Symbol(name)) => {
+                            imports.push(ImportEntry::ByName { name, hint: None })
+                        }
+                        None => {
+                            let mut value = [0; 8];
+                            value[..thunk_size]
+                                .copy_from_slice(&section_data[offset..offset + thunk_size]);
+                            let value = u64::from_le_bytes(value);
+                            // Skip the zero entry that ends the table.
+                            if value >> (thunk_size * 8 - 1) == 1 {
+                                imports.push(ImportEntry::ByOrdinal(value as u16));
+                            }
+                        }
+                    }
+                }
+            }
+            b"7" if relocations.is_empty() && !section_data.is_empty() => {
+                imports.push(ImportEntry::DllName(c_str(section_data)));
+            }
+            _ => {}
+        }
+    }
+    Some(imports)
+}
+
+/// The import structures of a member of a lib, as returned by [`lib_imports`].
+#[derive(Clone, Debug)]
+pub struct MemberImports {
+    pub name: String,
+    pub imports: Vec<ImportEntry>,
+}
+
+/// Read the import descriptors and import lookup table entries of each member
+/// with `.idata$` sections, which short imports don't have.
+pub fn lib_imports(lib_path: &OsStr) -> Result<Vec<MemberImports>, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+
+    let mut members = Vec::new();
+    for member in lib.members() {
+        let member = member?;
+        let data = member.data()?;
+        if classify(data) != MemberKind::ImportObject {
+            continue;
+        }
+        let name = member.name().into_owned();
+        let Some(imports) = member_imports(data) else {
+            warn!("could not read the imports of the member {name} in {}", lib_path.display());
+            continue;
+        };
+        members.push(MemberImports { name, imports });
+    }
+    Ok(members)
+}
+
+/// An entry of a lib's symbol index, as returned by [`symbol_index`].
+#[derive(Clone, Debug)]
+pub struct IndexEntry {
+    pub name: String,
+    /// The offset of the member header that the entry points to.
+    pub header_offset: u64,
+    /// The data offset and name of the member that the entry points to, or
+    /// the error from reading it if the entry doesn't point to the start of a member.
+    pub member: Result<(u64, String), object::Error>,
+}
+
+/// Read the entries of a lib's symbol index, along with the member that each
+/// entry points to.
+pub fn symbol_index(lib_path: &OsStr) -> Result<Vec<IndexEntry>, WinlibError> {
+    let data = read_file(lib_path)?;
+    let archive = parse_archive(&data, lib_path)?;
+
+    let index_error = |e| WinlibError::ObjectError {
+        msg: format!("could not read the symbol index of {}", lib_path.display()),
+        cause: e,
+    };
+    let Some(symbols) = archive.symbols().map_err(index_error)? else {
+        return Err(WinlibError::InvalidInput {
+            msg: format!("{} has no symbol index", lib_path.display()),
+        });
+    };
+    let mut entries = Vec::new();
+    for symbol in symbols {
+        let symbol = symbol.map_err(index_error)?;
+        let member = archive.member(symbol.offset()).map(|member| {
+            (member.file_range().0, String::from_utf8_lossy(member.name()).into_owned())
+        });
+        entries.push(IndexEntry {
+            name: String::from_utf8_lossy(symbol.name()).into_owned(),
+            header_offset: symbol.offset().0,
+            member,
+        });
+    }
+    Ok(entries)
+}
+
+/// Find the data of the `//` member that holds the long names of a COFF or GNU archive.
+///
+/// Returns the offset of the data and the data itself.
+fn long_name_table(data: &[u8]) -> Option<(usize, &[u8])> {
+    // Special members come first so stop at the first member that isn't one.
+    let mut offset = 8;
+    while let Some(header) = data.get(offset..offset + 60) {
+        let size: usize = std::str::from_utf8(&header[48..58]).ok()?.trim_end().parse().ok()?;
+        let start = offset + 60;
+        match header[..16].trim_ascii_end() {
+            b"//" => return Some((start, data.get(start..start.checked_add(size)?)?)),
+            b"/" | b"/SYM64/" => offset = start + size + size % 2,
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// The long name table of a lib and how each member refers to it, as returned
+/// by [`name_table`].
+#[derive(Clone, Debug, Default)]
+pub struct NameTable {
+    /// The offset and size of the data of the `//` member, if there is one.
+    pub table: Option<(usize, usize)>,
+    /// The offset of each name in the table, along with the name.
+    pub names: Vec<(usize, String)>,
+    /// The data offset, header reference and name of each member. The
+    /// reference is `None` for members whose name fits in the header.
+    pub members: Vec<(u64, Option<String>, String)>,
+}
+
+/// Read the long name table of a lib, along with the name reference in the
+/// header of each member.
+pub fn name_table(lib_path: &OsStr) -> Result<NameTable, WinlibError> {
+    let data = read_file(lib_path)?;
+    let archive = parse_archive(&data, lib_path)?;
+
+    let mut names = NameTable::default();
+    if let Some((table_offset, table)) = long_name_table(&data) {
+        names.table = Some((table_offset, table.len()));
+        let mut start = 0;
+        // COFF names end with a null and GNU names end with `/\n`.
+        for entry in table.split_inclusive(|&b| b == 0 || b == b'\n') {
+            let name = entry.strip_suffix(b"\0").or_else(|| entry.strip_suffix(b"/\n"));
+            if let Some(name) = name.filter(|name| !name.is_empty()) {
+                names.names.push((start, String::from_utf8_lossy(name).into_owned()));
+            }
+            start += entry.len();
+        }
+    }
+    for member in archive.members() {
+        let member = member.map_err(|e| WinlibError::ObjectError {
+            msg: format!("could not read archive member in {}", lib_path.display()),
+            cause: e,
+        })?;
+        let offset = member.file_range().0;
+        // The member header is 60 bytes and immediately precedes the data,
+        // except for BSD names which follow the header and are never in the table.
+        let header_name = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| data.get(offset.checked_sub(60)?..offset.checked_sub(44)?))
+            .unwrap_or_default()
+            .trim_ascii_end();
+        let reference = match header_name.strip_prefix(b"/") {
+            Some(digits) if !digits.is_empty() && digits.iter().all(u8::is_ascii_digit) => {
+                Some(String::from_utf8_lossy(header_name).into_owned())
+            }
+            _ => None,
+        };
+        let name = String::from_utf8_lossy(member.name()).into_owned();
+        names.members.push((offset, reference, name));
+    }
+    Ok(names)
+}
+
+/// A DLL imported by a lib, as returned by [`lib_dlls`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DllImports {
+    pub name: String,
+    /// The number of symbols imported from the DLL.
+    pub symbols: usize,
+    /// How many of those symbols are imported by ordinal.
+    pub ordinals: usize,
+}
+
+/// Find the DLLs imported by a lib along with the number of symbols imported
+/// from each, sorted by name.
+///
+/// If `import_kind` is given then only symbols of that type are counted.
+pub fn lib_dlls(
+    lib_path: &OsStr,
+    import_kind: Option<ImportKind>,
+) -> Result<Vec<DllImports>, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+
+    // DLL names are case-insensitive so key them by their lowercase name.
+    let mut dlls: BTreeMap<String, DllImports> = BTreeMap::new();
+    for member in lib.members() {
+        let member = member?;
+        let data = member.data()?;
+        if let Ok(file) = ImportFile::parse(data)
+            && import_kind.is_none_or(|kind| ImportKind::of(&file) == kind)
+        {
+            let name = String::from_utf8_lossy(file.dll());
+            let dll = dlls.entry(name.to_lowercase()).or_insert_with(|| DllImports {
+                name: name.into(),
+                symbols: 0,
+                ordinals: 0,
+            });
+            dll.symbols += 1;
+            if let ImportName::Ordinal(_) = file.import() {
+                dll.ordinals += 1;
+            }
+        }
+    }
+    Ok(dlls.into_values().collect())
+}
+
+/// Returns true for symbols that are part of the import machinery rather than
+/// something exported by the DLL.
+fn is_import_descriptor_symbol(name: &str) -> bool {
+    name.starts_with("__IMPORT_DESCRIPTOR_")
+        || name.starts_with("__imp_")
+        || name == "__NULL_IMPORT_DESCRIPTOR"
+        || name.ends_with("_NULL_THUNK_DATA")
+}
+
+/// Get the plain name from a stdcall decorated name such as `_Foo@4`.
+fn undecorate_stdcall(name: &str) -> Option<&str> {
+    let (name, args) = name.strip_prefix('_')?.rsplit_once('@')?;
+    let is_stdcall =
+        !name.is_empty() && !args.is_empty() && args.bytes().all(|b| b.is_ascii_digit());
+    is_stdcall.then_some(name)
+}
+
+/// Generate a module-definition file from the imports in a lib.
+///
+/// If `undecorate` is set then stdcall decorated x86 names such as `_Foo@4`
+/// are exported as `Foo=_Foo@4`.
+pub fn lib_to_def(lib_path: &OsStr, undecorate: bool) -> Result<String, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+
+    let mut dlls: Vec<String> = Vec::new();
+    let mut exports = Vec::new();
+    let mut seen = HashSet::new();
+    for member in lib.members() {
+        let member = member?;
+        let data = member.data()?;
+        let undecorate = undecorate && member_machine(data) == Some(pe::IMAGE_FILE_MACHINE_I386);
+        let export_name = |name: &str| match undecorate_stdcall(name).filter(|_| undecorate) {
+            Some(plain) => format!("{plain}={name}"),
+            None => name.to_owned(),
+        };
+        if let Ok(file) = ImportFile::parse(data) {
+            let dll = String::from_utf8_lossy(file.dll());
+            if !dlls.iter().any(|d| d.eq_ignore_ascii_case(&dll)) {
+                dlls.push(dll.into());
+            }
+            let name = String::from_utf8_lossy(file.symbol());
+            if !seen.insert(name.clone().into_owned()) {
+                continue;
+            }
+            let mut export = export_name(&name);
+            if let ImportName::Ordinal(ordinal) = file.import() {
+                export.push_str(&format!(" @{ordinal} NONAME"));
+            }
+            match file.import_type() {
+                ImportType::Code => {}
+                ImportType::Data => export.push_str(" DATA"),
+                ImportType::Const => export.push_str(" CONSTANT"),
+            }
+            exports.push(export);
+        } else if let Some(symbols) = member_symbols(data) {
+            for symbol in symbols {
+                // A weak external is only a default for a symbol defined elsewhere.
+                if matches!(symbol, MemberSymbol::Weak { .. }) {
+                    continue;
+                }
+                if !is_import_descriptor_symbol(symbol.name()) && seen.insert(symbol.name().into())
+                {
+                    exports.push(export_name(symbol.name()));
+                }
+            }
+        }
+    }
+
+    let mut def = String::new();
+    if let Some(dll) = dlls.first() {
+        if dlls.len() > 1 {
+            warn!(
+                "{} imports from multiple DLLs ({}); using {dll}",
+                lib_path.display(),
+                dlls.join(", ")
+            );
+        }
+        def.push_str(&format!("LIBRARY {dll}\n"));
+    }
+    def.push_str("EXPORTS\n");
+    for export in exports {
+        def.push_str(&format!("    {export}\n"));
+    }
+    Ok(def)
+}
+
+/// The contents of a module-definition file.
+struct ModuleDefinition {
+    library: Option<String>,
+    exports: Vec<COFFShortExport>,
+}
+
+/// Split a line of a module-definition file into tokens.
+///
+/// `=` and `==` are always separate tokens and quoted strings are a single token.
+fn def_tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '=' {
+            chars.next();
+            if chars.next_if_eq(&'=').is_some() {
+                tokens.push("==".into());
+            } else {
+                tokens.push("=".into());
+            }
+        } else if c == '"' {
+            chars.next();
+            tokens.push(chars.by_ref().take_while(|&c| c != '"').collect());
+        } else {
+            let mut token = String::new();
+            while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && c != '=') {
+                token.push(c);
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+/// Returns true if an x86 symbol name already has its C decoration.
+///
+/// This follows the rules used by LLVM for non-MinGW module-definition files.
+fn is_decorated(name: &str) -> bool {
+    name.starts_with(['@', '?']) || name.contains('@')
+}
+
+fn parse_def(def: &str, machine: MachineTypes) -> Result<ModuleDefinition, String> {
+    let mut library = None;
+    let mut exports = Vec::new();
+    let mut in_exports = false;
+    for (line_number, line) in def.lines().enumerate() {
+        let line_number = line_number + 1;
+        let line = line.split(';').next().unwrap_or_default();
+        let tokens = def_tokens(line);
+        let Some(first) = tokens.first() else { continue };
+        match first.as_str() {
+            "LIBRARY" | "NAME" => {
+                in_exports = false;
+                if let Some(name) = tokens.get(1) {
+                    let name = if Path::new(name).extension().is_some() {
+                        name.clone()
+                    } else {
+                        format!("{name}.dll")
+                    };
+                    library = Some(name);
+                }
+                continue;
+            }
+            "EXPORTS" => {
+                in_exports = true;
+                continue;
+            }
+            "HEAPSIZE" | "STACKSIZE" | "SECTIONS" | "VERSION" | "STUB" => {
+                in_exports = false;
+                continue;
+            }
+            _ if !in_exports => continue,
+            _ => {}
+        }
+
+        let mut export = COFFShortExport {
+            name: first.clone(),
+            ext_name: None,
+            symbol_name: None,
+            alias_target: None,
+            ordinal: 0,
+            noname: false,
+            data: false,
+            private: false,
+            constant: false,
+        };
+        let mut tokens = tokens[1..].iter();
+        while let Some(token) = tokens.next() {
+            match token.as_str() {
+                "=" => {
+                    let Some(name) = tokens.next() else {
+                        return Err(format!("line {line_number}: expected a name after `=`"));
+                    };
+                    export.ext_name = Some(mem::replace(&mut export.name, name.clone()));
+                }
+                "==" => {
+                    let Some(name) = tokens.next() else {
+                        return Err(format!("line {line_number}: expected a name after `==`"));
+                    };
+                    export.alias_target = Some(name.clone());
+                }
+                "NONAME" => export.noname = true,
+                "DATA" => export.data = true,
+                "PRIVATE" => export.private = true,
+                "CONSTANT" => export.constant = true,
+                token if token.starts_with('@') => {
+                    export.ordinal = token[1..]
+                        .parse()
+                        .map_err(|_| format!("line {line_number}: invalid ordinal `{token}`"))?;
+                }
+                token => return Err(format!("line {line_number}: unexpected `{token}`")),
+            }
+        }
+        if export.noname && export.ordinal == 0 {
+            return Err(format!("line {line_number}: NONAME requires an ordinal"));
+        }
+        if machine == MachineTypes::I386 {
+            for name in [Some(&mut export.name), export.ext_name.as_mut()].into_iter().flatten() {
+                if !is_decorated(name) {
+                    name.insert(0, '_');
+                }
+            }
+        }
+        exports.push(export);
+    }
+    Ok(ModuleDefinition { library, exports })
+}
+
+/// Create an import lib for `dll` from a module-definition file.
+///
+/// If `dll` is `None` then the name given by the `LIBRARY` statement is used.
+/// Fails if `out_lib` exists, unless `force` is set.
+pub fn lib_from_def(
+    def_path: &OsStr,
+    out_lib: &OsStr,
+    dll: Option<&str>,
+    machine: MachineTypes,
+    force: bool,
+) -> Result<(), WinlibError> {
+    if !force {
+        check_overwrite(def_path, out_lib)?;
+    }
+    let def = fs::read_to_string(def_path).map_err(|e| WinlibError::IoError {
+        msg: format!("cannot read {}", def_path.display()),
+        cause: e,
+    })?;
+    let def = parse_def(&def, machine).map_err(|e| WinlibError::InvalidInput {
+        msg: format!("invalid module-definition file {}: {e}", def_path.display()),
+    })?;
+    let Some(dll) = dll.map(String::from).or(def.library) else {
+        return Err(WinlibError::InvalidInput {
+            msg: format!(
+                "{} has no LIBRARY statement\nhelp: use --dll to set the DLL name",
+                def_path.display()
+            ),
+        });
+    };
+
+    let mut writer = Cursor::new(Vec::with_capacity(64 * 1024));
+    ar_archive_writer::write_import_library(&mut writer, &dll, &def.exports, machine, false, false)
+        .map_err(|e| WinlibError::IoError {
+            msg: "could not create new library file".into(),
+            cause: e,
+        })?;
+    write_lib(out_lib, writer.get_ref())?;
+
+    Ok(())
+}
+
+/// Match `name` against a shell-style wildcard pattern.
+///
+/// `*` matches any sequence of characters and `?` matches any single character.
+/// Matching is ASCII case-insensitive, as with Windows file names.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let name = name.as_bytes();
+    let (mut p, mut n) = (0, 0);
+    // The position of the last `*` seen in the pattern and the position in
+    // `name` it is currently matched up to.
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, n));
+                p += 1;
+                continue;
+            }
+            Some(b'?') => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            Some(c) if c.eq_ignore_ascii_case(&name[n]) => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((star, matched)) => {
+                p = star + 1;
+                n = matched + 1;
+                backtrack = Some((star, n));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// The parts of each member that [`list_lib`] reads, other than its header.
+///
+/// Reading these means parsing or hashing the member's data, so each is only
+/// done when asked for.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ListFields {
+    /// Set [`MemberInfo::kind`].
+    pub kind: bool,
+    /// Set [`MemberInfo::machine`].
+    pub machine: bool,
+    /// Set [`MemberInfo::dll`] and [`MemberInfo::import_kind`].
+    pub import: bool,
+    /// Set [`MemberInfo::symbols`].
+    pub symbols: bool,
+    /// Set [`MemberInfo::sections`].
+    pub sections: bool,
+    /// Set [`MemberInfo::hash`] to a hash of the member's data.
+    pub hash: Option<HashKind>,
+}
+impl ListFields {
+    /// Whether any of the fields needs the member's data.
+    fn need_data(&self) -> bool {
+        self.kind
+            || self.machine
+            || self.import
+            || self.symbols
+            || self.sections
+            || self.hash.is_some()
+    }
+}
+
+/// An archive member, as returned by [`list_lib`].
+///
+/// Fields that weren't asked for in [`ListFields`] are `None` or empty.
+#[derive(Clone, Debug)]
+pub struct MemberInfo {
+    /// The offset of the member's data in the archive, just after its header.
+    pub offset: u64,
+    /// The size of the member's data.
+    pub size: u64,
+    pub name: String,
+    /// The modification time as a Unix timestamp.
+    pub mtime: Option<u64>,
+    pub uid: Option<u64>,
+    pub gid: Option<u64>,
+    pub mode: Option<u64>,
+    pub kind: Option<MemberKind>,
+    /// The COFF machine type.
+    pub machine: Option<u16>,
+    /// The DLL that a short import member imports from.
+    pub dll: Option<String>,
+    /// The type of symbol that a short import member imports.
+    pub import_kind: Option<ImportKind>,
+    /// The symbols provided by the member.
+    pub symbols: Vec<MemberSymbol>,
+    /// The name and raw data size of each section in a COFF member.
+    pub sections: Vec<(String, u32)>,
+    /// The hash of the member's data.
+    pub hash: Option<String>,
+}
+
+/// Read the members of the lib at `lib_path`.
+///
+/// If `filter` is given then only members whose name matches it are returned.
+/// Only the parts of each member given by `fields` are read. Fails if any
+/// member is larger than `max_member_size` bytes.
+pub fn list_lib(
+    lib_path: &OsStr,
+    filter: Option<&Pattern>,
+    fields: &ListFields,
+    max_member_size: Option<u64>,
+) -> Result<Vec<MemberInfo>, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+
+    let mut members = Vec::new();
+    for member in lib.members() {
+        let member = member?;
+        check_member_size(lib_path, &member.member, max_member_size)?;
+        let name = member.name();
+        if let Some(filter) = filter
+            && !filter.matches_name(&name)
+        {
+            continue;
+        }
+        // Only reading the headers means a truncated member can still be listed.
+        let member_data = if fields.need_data() { Some(member.data()?) } else { None };
+        let read = |wanted: bool| member_data.filter(|_| wanted);
+        let import = read(fields.import).and_then(|data| ImportFile::parse(data).ok());
+        members.push(MemberInfo {
+            offset: member.offset(),
+            size: member.size(),
+            name: name.into_owned(),
+            mtime: member.member.date(),
+            uid: member.member.uid(),
+            gid: member.member.gid(),
+            mode: member.member.mode(),
+            kind: read(fields.kind).map(classify),
+            machine: read(fields.machine).and_then(member_machine),
+            dll: import.as_ref().map(|file| String::from_utf8_lossy(file.dll()).into_owned()),
+            import_kind: import.as_ref().map(ImportKind::of),
+            symbols: read(fields.symbols).and_then(member_symbols).unwrap_or_default(),
+            sections: read(fields.sections).and_then(member_sections).unwrap_or_default(),
+            hash: fields.hash.zip(member_data).map(|(hash, data)| hash.hash(data)),
+        });
+    }
+    Ok(members)
+}
+
+/// Turn an archive member name into a file name that's safe to write.
+///
+/// Any directory components are stripped so members can't be written outside
+/// of the output directory.
+fn member_file_name(name: &str) -> &str {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    match name {
+        "" | "." | ".." => "member",
+        name => name,
+    }
+}
+
+/// A summary of a lib, as returned by [`lib_info`].
+#[derive(Clone, Debug)]
+pub struct LibInfo {
+    /// The archive format, such as `coff` or `gnu`.
+    pub kind: &'static str,
+    pub members: usize,
+    /// The number of entries in the symbol index, or `None` if there's no index.
+    pub index_symbols: Option<usize>,
+    /// The machine types of the members.
+    pub machines: BTreeSet<u16>,
+    /// The DLLs imported from, in lowercase.
+    pub dlls: BTreeSet<String>,
+    /// The size of the lib in bytes.
+    pub size: u64,
+}
+
+/// Summarise the lib at `lib_path`.
+pub fn lib_info(lib_path: &OsStr) -> Result<LibInfo, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+    let archive = lib.archive();
+
+    let kind = match archive.kind() {
+        ArchiveKind::Coff => "coff",
+        ArchiveKind::Gnu => "gnu",
+        ArchiveKind::Gnu64 => "gnu64",
+        ArchiveKind::Bsd => "bsd",
+        ArchiveKind::Bsd64 => "bsd64",
+        ArchiveKind::AixBig => "aix",
+        _ => "unknown",
+    };
+    let index_symbols = archive
+        .symbols()
+        .map_err(|e| WinlibError::ObjectError {
+            msg: format!("could not read the symbol index in {}", lib_path.display()),
+            cause: e,
+        })?
+        .map(|symbols| symbols.count());
+    let mut info = LibInfo {
+        kind,
+        members: 0,
+        index_symbols,
+        machines: BTreeSet::new(),
+        dlls: BTreeSet::new(),
+        size: lib.data.len() as u64,
+    };
+    for member in lib.members() {
+        let member = member?;
+        let member_data = member.data()?;
+        info.members += 1;
+        if let Some(machine) = member_machine(member_data) {
+            info.machines.insert(machine);
+        }
+        if let Ok(file) = ImportFile::parse(member_data) {
+            info.dlls.insert(String::from_utf8_lossy(file.dll()).to_lowercase());
+        }
+    }
+    Ok(info)
+}
+
+/// The symbols that a member should have in the symbol index.
+///
+/// Short imports are indexed under both their `__imp_` name and, unless they
+/// import data, their plain name.
+fn index_symbols(data: &[u8]) -> Vec<String> {
+    if let Ok(file) = ImportFile::parse(data) {
+        let name = String::from_utf8_lossy(file.symbol());
+        let mut symbols = vec![format!("__imp_{name}")];
+        if file.import_type() != ImportType::Data {
+            symbols.push(name.into_owned());
+        }
+        symbols
+    } else {
+        member_symbols(data)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|symbol| symbol.name().to_owned())
+            .collect()
+    }
+}
+
+/// A difference between a lib's symbol index and the symbols its members
+/// define, as found by [`verify_symbol_index`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IndexMismatch {
+    /// An entry that points to an offset which isn't the start of a member.
+    NotAMember { name: String, header_offset: u64 },
+    /// A symbol defined by the member at `offset` that isn't in the index.
+    Missing { name: String, member: String, offset: u64 },
+    /// An entry that points to the member at `offset`, which doesn't define it.
+    Stale { name: String, offset: u64 },
+}
+
+/// Check the symbol index of the lib at `lib_path` against the symbols defined by each member.
+///
+/// A symbol defined by more than one member only needs an entry for one of
+/// them. Returns the mismatches found, which is empty if the index is correct.
+pub fn verify_symbol_index(lib_path: &OsStr) -> Result<Vec<IndexMismatch>, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+    let archive = lib.archive();
+
+    let index_error = |e| WinlibError::ObjectError {
+        msg: format!("could not read the symbol index of {}", lib_path.display()),
+        cause: e,
+    };
+    let Some(symbols) = archive.symbols().map_err(index_error)? else {
+        return Err(WinlibError::InvalidInput {
+            msg: format!("{} has no symbol index", lib_path.display()),
+        });
+    };
+    let mut mismatches = Vec::new();
+    // The data offset and name of each index entry that points to a member.
+    let mut index = HashSet::new();
+    let mut index_names = HashSet::new();
+    for symbol in symbols {
+        let symbol = symbol.map_err(index_error)?;
+        let name = String::from_utf8_lossy(symbol.name()).into_owned();
+        match archive.member(symbol.offset()) {
+            Ok(member) => {
+                index_names.insert(name.clone());
+                index.insert((member.file_range().0, name));
+            }
+            Err(_) => {
+                let header_offset = symbol.offset().0;
+                mismatches.push(IndexMismatch::NotAMember { name, header_offset });
+            }
+        }
+    }
+
+    let mut defined = HashSet::new();
+    for member in lib.members() {
+        let member = member?;
+        let offset = member.offset();
+        let member_data = member.data()?;
+        let member_name = member.name();
+        for name in index_symbols(member_data) {
+            if !index_names.contains(&name) {
+                let member = member_name.clone().into_owned();
+                mismatches.push(IndexMismatch::Missing { name: name.clone(), member, offset });
+            }
+            defined.insert((offset, name));
+        }
+    }
+    let mut stale: Vec<_> = index.difference(&defined).cloned().collect();
+    stale.sort();
+    mismatches
+        .extend(stale.into_iter().map(|(offset, name)| IndexMismatch::Stale { name, offset }));
+    Ok(mismatches)
+}
+
+/// Check that every member of the lib at `lib_path` can be read and parsed.
+///
+/// If `target` is given then members for a different COFF machine type are
+/// warned about, or are an error if `strict` is set.
+///
+/// Returns the number of members, or an error describing the first member that failed.
+pub fn verify_lib(
+    lib_path: &OsStr,
+    target: Option<u16>,
+    strict: bool,
+) -> Result<usize, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+
+    let mut count = 0;
+    for member in lib.members() {
+        let member = member?;
+        let member_data = member.data()?;
+        classify_member(member_data).map_err(|e| WinlibError::ObjectError {
+            msg: format!(
+                "unrecognised archive member at {:#x} in {}",
+                member.offset(),
+                lib_path.display()
+            ),
+            cause: e,
+        })?;
+        if let Some(target) = target
+            && let Some(msg) = target_mismatch(lib_path, &member.member, member_data, target)
+        {
+            if strict {
+                return Err(WinlibError::InvalidInput { msg });
+            }
+            warn!("{msg}");
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Write members of the lib at `lib_path` to files in `out_dir`.
+///
+/// If `pattern` is given then only members whose name matches it are written.
+pub fn extract_lib(
+    lib_path: &OsStr,
+    out_dir: &Path,
+    pattern: Option<&Pattern>,
+) -> Result<(), WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+
+    let mut members = Vec::new();
+    let mut name_counts: HashMap<String, usize> = HashMap::new();
+    for member in lib.members() {
+        let member = member?;
+        let name = member.name();
+        if let Some(pattern) = pattern
+            && !pattern.matches_name(&name)
+        {
+            continue;
+        }
+        let file_name = member_file_name(&name).to_owned();
+        *name_counts.entry(file_name.to_ascii_lowercase()).or_default() += 1;
+        members.push((member, file_name));
+    }
+
+    fs::create_dir_all(out_dir).map_err(|e| WinlibError::IoError {
+        msg: format!("unable to create directory {}", out_dir.display()),
+        cause: e,
+    })?;
+    for (member, file_name) in members {
+        let offset = member.offset();
+        let data = member.data()?;
+        // Member names aren't unique so disambiguate them using the offset.
+        let path = if name_counts[&file_name.to_ascii_lowercase()] > 1 {
+            let path = Path::new(&file_name);
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            match path.extension() {
+                Some(ext) => out_dir.join(format!("{stem}-{offset:#X}.{}", ext.display())),
+                None => out_dir.join(format!("{stem}-{offset:#X}")),
+            }
+        } else {
+            out_dir.join(file_name)
+        };
+        fs::write(&path, data).map_err(|e| WinlibError::IoError {
+            msg: format!("unable to write member to {}", path.display()),
+            cause: e,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Find the lib that an import object belongs to in [`split_lib`], given the
+/// libs of the imported DLLs keyed by their lowercase DLL name and stem.
+///
+/// The DLL is named by the import descriptor or DLL name in the object's
+/// `.idata$` sections, or by its descriptor or null thunk symbol. The null
+/// import descriptor is shared by every DLL so it's matched by the member's name.
+fn import_object_lib<'a>(
+    data: &[u8],
+    member_name: &str,
+    dlls: &'a HashMap<String, String>,
+    stems: &HashMap<String, &'a String>,
+) -> Option<&'a String> {
+    let by_dll = |dll: &str| dlls.get(&member_file_name(dll).to_lowercase());
+    for import in member_imports(data).unwrap_or_default() {
+        if let ImportEntry::Descriptor(dll) | ImportEntry::DllName(dll) = import
+            && let Some(lib) = by_dll(&dll)
+        {
+            return Some(lib);
+        }
+    }
+    let symbols = member_symbols(data).unwrap_or_default();
+    for symbol in &symbols {
+        let name = symbol.name();
+        let stem = name.strip_prefix("__IMPORT_DESCRIPTOR_").or_else(|| {
+            name.strip_prefix('\x7f').and_then(|name| name.strip_suffix("_NULL_THUNK_DATA"))
+        });
+        if let Some(&lib) = stem.and_then(|stem| stems.get(&stem.to_lowercase())) {
+            return Some(lib);
+        }
+    }
+    symbols
+        .iter()
+        .any(|symbol| symbol.name() == "__NULL_IMPORT_DESCRIPTOR")
+        .then(|| by_dll(member_name))
+        .flatten()
+}
+
+/// Split the lib at `lib_path` into one lib per imported DLL, written to `out_dir`.
+///
+/// Each lib is named after the DLL, so imports from `kernel32.dll` are written to
+/// `kernel32.lib`. Import objects for an imported DLL, such as its import
+/// descriptor, go in the same lib as that DLL's imports. Any remaining members
+/// are written to `misc.lib`, or `misc-1.lib` and so on if a DLL's lib already
+/// has that name.
+///
+/// The libs are written in `archive_format`, which defaults to the format of
+/// the lib being split. Fails without writing anything if one of the libs
+/// already exists, unless `force` is set. Returns the paths of the libs that
+/// were written.
+pub fn split_lib(
+    lib_path: &OsStr,
+    out_dir: &Path,
+    archive_format: Option<ArchiveFormat>,
+    force: bool,
+) -> Result<Vec<PathBuf>, WinlibError> {
+    let lib = LibReader::open(lib_path)?;
+    let kind = archive_format.unwrap_or_else(|| lib.format()).kind();
+
+    let mut members = Vec::new();
+    // Maps the lowercase name of each imported DLL to the name of its lib.
+    let mut dlls: HashMap<String, String> = HashMap::new();
+    for member in lib.members() {
+        let member = member?;
+        let member_data = member.data()?;
+        if let Ok(file) = ImportFile::parse(member_data) {
+            let dll = String::from_utf8_lossy(file.dll());
+            let dll = member_file_name(&dll);
+            let stem = Path::new(dll).file_stem().unwrap_or_default().to_string_lossy();
+            dlls.entry(dll.to_lowercase()).or_insert_with(|| format!("{stem}.lib"));
+        }
+        members.push((member, member_data));
+    }
+
+    // The descriptor and null thunk symbols are named after the DLL's stem, as is its lib.
+    let stems: HashMap<String, &String> = dlls
+        .values()
+        .map(|lib| (lib.strip_suffix(".lib").unwrap_or(lib).to_lowercase(), lib))
+        .collect();
+    let mut misc = String::from("misc.lib");
+    for n in 1.. {
+        if !dlls.values().any(|lib| lib.eq_ignore_ascii_case(&misc)) {
+            break;
+        }
+        misc = format!("misc-{n}.lib");
+    }
+
+    // Libs are keyed by their lowercase name so DLLs differing only in case share a lib.
+    let mut libs: BTreeMap<String, (String, Vec<NewArchiveMember<'_>>)> = BTreeMap::new();
+    for (member, member_data) in members {
+        let lib_name = match ImportFile::parse(member_data) {
+            Ok(file) => {
+                let dll = String::from_utf8_lossy(file.dll());
+                dlls.get(&member_file_name(&dll).to_lowercase())
+            }
+            Err(_) if classify(member_data) == MemberKind::ImportObject => {
+                import_object_lib(member_data, &member.name(), &dlls, &stems)
+            }
+            Err(_) => None,
+        };
+        let lib_name = lib_name.map_or(misc.as_str(), String::as_str);
+        libs.entry(lib_name.to_lowercase())
+            .or_insert_with(|| (lib_name.to_owned(), Vec::new()))
+            .1
+            .push(new_archive_member(&member.member, member_data));
+    }
+
+    if misc != "misc.lib" && libs.contains_key(&misc) {
+        warn!(
+            "the members that don't belong to a DLL are written to {misc} because misc.lib is used by an imported DLL"
+        );
+    }
+    if !force {
+        for (lib_name, _) in libs.values() {
+            check_overwrite(lib_path, out_dir.join(lib_name).as_os_str())?;
+        }
+    }
+    fs::create_dir_all(out_dir).map_err(|e| WinlibError::IoError {
+        msg: format!("unable to create directory {}", out_dir.display()),
+        cause: e,
+    })?;
+    let mut paths = Vec::new();
+    for (lib_name, members) in libs.into_values() {
+        let mut writer = Cursor::new(Vec::with_capacity(64 * 1024));
+        ar_archive_writer::write_archive_to_stream(
+            &mut writer,
+            &members,
+            kind,
+            false,
+            needs_ec_symbols(&members, kind),
+        )
+        .map_err(|e| WinlibError::IoError {
+            msg: "could not create new library file".into(),
+            cause: e,
+        })?;
+        let path = out_dir.join(lib_name);
+        write_lib(path.as_os_str(), writer.get_ref())?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
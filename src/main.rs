@@ -1,252 +1,1399 @@
-use clap::ArgAction;
+use clap::error::ErrorKind;
+use clap::{ArgAction, ArgMatches};
 use core::num::ParseIntError;
-use object::coff::{CoffFile, ImportFile};
-use object::pe::ImageFileHeader;
-use object::read::archive::ArchiveFile;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::ffi::{OsStr, OsString};
-use std::fmt;
 use std::fs;
-use std::io::{self, Cursor};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
 
-#[derive(Debug)]
-enum WinlibError {
-    ObjectError { msg: String, cause: object::Error },
-    IoError { msg: String, cause: io::Error },
+use ar_archive_writer::MachineTypes;
+use winlib::{
+    ArchiveFormat, CreateOptions, DllImports, HashKind, ImportKind, IndexEntry, IndexMismatch,
+    ListFields, MemberImports, MemberInfo, MemberKind, MemberRef, MemberSymbol, MemberSymbols,
+    MergeOptions, NameTable, Pattern, Stats, SymbolChange, SymbolReferences, WinlibError,
+    add_objects, check_overwrite, create_lib, create_lib_from, diff_libs, extract_lib,
+    format_timestamp, json_string, lib_dlls, lib_from_def, lib_imports, lib_info, lib_to_def,
+    list_lib, machine_name, merge_libs, name_table, split_lib, symbol_index, symbols_by_member,
+    target_machine, touch_lib, undefined_symbols, verify_lib, verify_symbol_index,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ListFormat {
+    Table,
+    Json,
+    Csv,
 }
-impl fmt::Display for WinlibError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::ObjectError { msg, cause } => {
-                write!(f, "{msg}\ncause: {cause}")
-            }
-            Self::IoError { msg, cause } => {
-                write!(f, "{msg}\ncause: {cause}")
-            }
-        }
+
+struct ListOptions {
+    format: ListFormat,
+    filter: Option<Pattern>,
+    symbols: bool,
+    sections: bool,
+    dll: bool,
+    arch: bool,
+    kind: bool,
+    verbose: bool,
+    check_duplicates: bool,
+    hash: Option<HashKind>,
+    group_by_dll: bool,
+    color: bool,
+    quiet: bool,
+}
+
+/// The number of hexadecimal digits in a hash.
+fn hash_width(hash: HashKind) -> usize {
+    match hash {
+        HashKind::Sha256 => 64,
+        HashKind::Crc32 => 8,
     }
 }
-impl Error for WinlibError {}
 
-struct CreateOptions {
-    exclude_idata: bool,
-    exclude_offsets: Vec<u32>,
-    save_excluded: Option<OsString>,
+/// The ANSI SGR code used for dimmed text, such as member sizes.
+const DIM: &str = "2";
+
+/// The ANSI SGR code used to color the names of `kind` of member.
+fn kind_sgr(kind: MemberKind) -> &'static str {
+    match kind {
+        MemberKind::Import => "36",
+        MemberKind::ImportObject => "35",
+        MemberKind::DelayImportObject => "33",
+        MemberKind::Object => "32",
+        MemberKind::Unknown => "31",
+    }
 }
 
-fn create_lib(
-    from_lib: &OsStr,
-    out_lib: &OsStr,
-    options: &CreateOptions,
-) -> Result<(), WinlibError> {
-    let extracted_lib = options.save_excluded.as_deref();
-    let data = fs::read(from_lib).map_err(|e| WinlibError::IoError {
-        msg: format!("cannot read {}", from_lib.display()),
-        cause: e,
-    })?;
-    let archive = ArchiveFile::parse(&*data).map_err(|e| WinlibError::ObjectError {
-        msg: format!("not a recognised archive file: {}", from_lib.display()),
-        cause: e,
-    })?;
+/// Wrap `text` in the ANSI escape sequence for the SGR code `sgr` if `color` is set.
+fn paint<'a>(text: &'a str, sgr: &str, color: bool) -> Cow<'a, str> {
+    if color { format!("\x1b[{sgr}m{text}\x1b[0m").into() } else { text.into() }
+}
 
-    let mut extracted_members = Vec::new();
-    let mut included_members = Vec::new();
+/// Quote a string for use in CSV output, if necessary.
+fn csv_field(s: &str) -> Cow<'_, str> {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\"")).into()
+    } else {
+        s.into()
+    }
+}
 
-    for member in archive.members() {
-        let member = member.map_err(|e| WinlibError::ObjectError {
-            msg: format!("could not read archive member in {}", from_lib.display()),
-            cause: e,
-        })?;
-        let data = member.data(&*data).map_err(|e| WinlibError::ObjectError {
-            msg: format!(
-                "could not get data from archive member at {:#x} in {}",
-                member.file_range().0,
-                from_lib.display()
-            ),
-            cause: e,
-        })?;
-        let mut exclude = false;
-        if options.exclude_offsets.contains(&(member.file_range().0 as u32)) {
-            exclude = true;
-        } else if options.exclude_idata {
-            match CoffFile::<_, ImageFileHeader>::parse(data) {
-                Ok(file) => {
-                    let strings = file.coff_symbol_table().strings();
-                    for section in file.coff_section_table().iter() {
-                        let name = section.name(strings).map_err(|e| WinlibError::ObjectError {
-                            msg: format!(
-                                "unable to retrieve section name at {:#x} in {}",
-                                member.file_range().0,
-                                from_lib.display()
-                            ),
-                            cause: e,
-                        })?;
-                        if name.starts_with(b".idata$") {
-                            exclude = true;
-                            break;
+/// Print the members returned by [`list_lib`].
+///
+/// Fails if `--check-duplicates` was given and a symbol is defined more than once.
+fn print_members(
+    out: &mut impl Write,
+    members: &[MemberInfo],
+    options: &ListOptions,
+) -> io::Result<ExitCode> {
+    match options.format {
+        ListFormat::Table if members.is_empty() => {
+            writeln!(out, "(no members)")?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        ListFormat::Table => {
+            let mut header = format!("{:>10}  {:>10}", "offset", "size");
+            if options.verbose {
+                header.push_str(&format!(
+                    "  {:<20}  {:>6}  {:>6}  {:>6}",
+                    "mtime", "uid", "gid", "mode"
+                ));
+            }
+            if options.kind {
+                header.push_str(&format!("  {:<19}", "type"));
+            }
+            if options.arch {
+                header.push_str(&format!("  {:<8}", "arch"));
+            }
+            if options.dll {
+                header.push_str(&format!("  {:<16}", "dll"));
+            }
+            if let Some(hash) = options.hash {
+                header.push_str(&format!("  {:<width$}", hash.as_str(), width = hash_width(hash)));
+            }
+            writeln!(out, "{header}  member name")?;
+        }
+        ListFormat::Json => writeln!(out, "[")?,
+        ListFormat::Csv => {
+            let mut header = String::from("offset,size");
+            if options.verbose {
+                header.push_str(",mtime,uid,gid,mode");
+            }
+            if options.kind {
+                header.push_str(",type");
+            }
+            if options.arch {
+                header.push_str(",arch");
+            }
+            if options.dll {
+                header.push_str(",dll");
+            }
+            if let Some(hash) = options.hash {
+                header.push_str(&format!(",{}", hash.as_str()));
+            }
+            header.push_str(",name");
+            if options.symbols {
+                header.push_str(",symbols");
+            }
+            if options.sections {
+                header.push_str(",sections");
+            }
+            writeln!(out, "{header}")?;
+        }
+    }
+    let mut first = true;
+    let mut total_size = 0;
+    let mut definitions: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+    let mut ordered: Vec<&MemberInfo> = members.iter().collect();
+    if options.group_by_dll {
+        // Objects go after the imports. The sort is stable so each group keeps the lib's order.
+        ordered.sort_by_cached_key(|member| {
+            (member.dll.is_none(), member.dll.as_deref().map(str::to_ascii_lowercase))
+        });
+    }
+    let mut group = None;
+    for member in ordered {
+        let MemberInfo { offset, size, name, .. } = member;
+        total_size += size;
+        if options.group_by_dll {
+            let dll = member.dll.as_deref().map(str::to_ascii_lowercase);
+            if group.as_ref() != Some(&dll) {
+                if group.is_some() {
+                    writeln!(out)?;
+                }
+                writeln!(out, "{}:", member.dll.as_deref().unwrap_or("(objects)"))?;
+                group = Some(dll);
+            }
+        }
+        if options.check_duplicates {
+            // A weak external is overridden by a definition rather than clashing with it.
+            for symbol in &member.symbols {
+                if !matches!(symbol, MemberSymbol::Weak { .. }) {
+                    definitions.entry(symbol.name()).or_default().push(*offset);
+                }
+            }
+        }
+        let symbols = if options.symbols { &member.symbols[..] } else { &[] };
+        let dll = member.dll.as_deref().filter(|_| options.dll);
+        let mtime = match member.mtime {
+            Some(0) | None => "-".into(),
+            Some(date) => format_timestamp(date),
+        };
+        let field = |value: Option<u64>| value.map_or("-".into(), |v| v.to_string());
+        let (uid, gid) = (field(member.uid), field(member.gid));
+        let mode = member.mode.map_or("-".into(), |mode| format!("{mode:o}"));
+        let kind = member.kind.filter(|_| options.kind).map(MemberKind::as_str);
+        let arch = options.arch.then(|| member.machine.map_or("-".into(), machine_name));
+        match options.format {
+            ListFormat::Table => {
+                let size = format!("{size:>#10X}");
+                let mut row = format!("{offset:>#10X}  {}", paint(&size, DIM, options.color));
+                if options.verbose {
+                    row.push_str(&format!("  {mtime:<20}  {uid:>6}  {gid:>6}  {mode:>6}"));
+                }
+                if let Some(kind) = kind {
+                    row.push_str(&format!("  {kind:<19}"));
+                }
+                if let Some(arch) = &arch {
+                    row.push_str(&format!("  {arch:<8}"));
+                }
+                if options.dll {
+                    row.push_str(&format!("  {:<16}", dll.unwrap_or("")));
+                }
+                if let Some(hash) = &member.hash {
+                    row.push_str(&format!("  {hash}"));
+                }
+                let name = paint(name, member.kind.map_or("", kind_sgr), options.color);
+                writeln!(out, "{row}  {name}")?;
+                for symbol in symbols {
+                    match symbol {
+                        MemberSymbol::Defined(name) => writeln!(out, "{:24}    {name}", "")?,
+                        MemberSymbol::Weak { name, target } => {
+                            writeln!(out, "{:24}    weak {name} -> {target}", "")?
+                        }
+                        MemberSymbol::Import { name, dll, ordinal: None } => {
+                            writeln!(out, "{:24}    {name} ({dll})", "")?
+                        }
+                        MemberSymbol::Import { name, dll, ordinal: Some(ordinal) } => {
+                            writeln!(out, "{:24}    {name} @{ordinal} NONAME ({dll})", "")?
                         }
                     }
                 }
-                Err(e) => {
-                    if ImportFile::parse(data).is_ok() {
-                        exclude = true;
-                    } else {
-                        // maybe we should just warn here?
-                        return Err(WinlibError::ObjectError {
-                            msg: format!(
-                                "unrecognised archive member at {:#x} in {}",
-                                member.file_range().0,
-                                from_lib.display()
-                            ),
-                            cause: e,
-                        });
+                if options.sections && member.kind == Some(MemberKind::Import) {
+                    writeln!(out, "{:24}    <short import>", "")?;
+                } else if options.sections {
+                    for (name, size) in &member.sections {
+                        writeln!(out, "{:24}    {name:<16}  {size:>#10X}", "")?;
                     }
                 }
             }
+            ListFormat::Json => {
+                if !first {
+                    writeln!(out, ",")?;
+                }
+                let name = json_string(name);
+                let mut entry = format!(r#"{{"offset": {offset}, "size": {size}, "name": {name}"#);
+                if options.verbose {
+                    let mtime = if mtime == "-" { "null".into() } else { json_string(&mtime) };
+                    let field = |value: Option<u64>| value.map_or("null".into(), |v| v.to_string());
+                    let (uid, gid, mode) =
+                        (field(member.uid), field(member.gid), field(member.mode));
+                    entry.push_str(&format!(
+                        r#", "mtime": {mtime}, "uid": {uid}, "gid": {gid}, "mode": {mode}"#
+                    ));
+                }
+                if let Some(kind) = kind {
+                    entry.push_str(&format!(r#", "type": {}"#, json_string(kind)));
+                }
+                if let Some(arch) = &arch {
+                    entry.push_str(&format!(r#", "arch": {}"#, json_string(arch)));
+                }
+                if options.dll {
+                    let dll = dll.map_or("null".into(), json_string);
+                    entry.push_str(&format!(r#", "dll": {dll}"#));
+                }
+                if let (Some(kind), Some(hash)) = (options.hash, &member.hash) {
+                    entry.push_str(&format!(r#", "{}": "{hash}""#, kind.as_str()));
+                }
+                if options.symbols {
+                    let symbols: Vec<_> = symbols.iter().map(|s| json_string(s.name())).collect();
+                    entry.push_str(&format!(r#", "symbols": [{}]"#, symbols.join(", ")));
+                }
+                if options.sections {
+                    let sections: Vec<_> = member
+                        .sections
+                        .iter()
+                        .map(|(name, size)| {
+                            format!(r#"{{"name": {}, "size": {size}}}"#, json_string(name))
+                        })
+                        .collect();
+                    entry.push_str(&format!(r#", "sections": [{}]"#, sections.join(", ")));
+                }
+                write!(out, "  {entry}}}")?;
+            }
+            ListFormat::Csv => {
+                let mut row = format!("{offset},{size}");
+                if options.verbose {
+                    row.push_str(&format!(",{mtime},{uid},{gid},{mode}"));
+                }
+                if let Some(kind) = kind {
+                    row.push_str(&format!(",{kind}"));
+                }
+                if let Some(arch) = &arch {
+                    row.push_str(&format!(",{}", csv_field(arch)));
+                }
+                if options.dll {
+                    row.push_str(&format!(",{}", csv_field(dll.unwrap_or(""))));
+                }
+                if let Some(hash) = &member.hash {
+                    row.push_str(&format!(",{hash}"));
+                }
+                row.push_str(&format!(",{}", csv_field(name)));
+                if options.symbols {
+                    let symbols: Vec<_> = symbols.iter().map(|s| s.name()).collect();
+                    row.push_str(&format!(",{}", csv_field(&symbols.join(" "))));
+                }
+                if options.sections {
+                    let sections: Vec<_> = member
+                        .sections
+                        .iter()
+                        .map(|(name, size)| format!("{name}={size}"))
+                        .collect();
+                    row.push_str(&format!(",{}", csv_field(&sections.join(" "))));
+                }
+                writeln!(out, "{row}")?;
+            }
+        }
+        first = false;
+    }
+    match options.format {
+        ListFormat::Table if options.quiet => {}
+        ListFormat::Table => {
+            let count = members.len();
+            let plural = if count == 1 { "" } else { "s" };
+            write!(out, "{count} member{plural}, total {total_size:#X} bytes")?;
+            if let Some(hash) = options.hash {
+                // Sorting makes the manifest hash independent of the order of the members.
+                let mut manifest: Vec<_> = members
+                    .iter()
+                    .map(|member| {
+                        format!("{}  {}\n", member.hash.as_deref().unwrap_or(""), member.name)
+                    })
+                    .collect();
+                manifest.sort();
+                write!(
+                    out,
+                    ", {} manifest {}",
+                    hash.as_str(),
+                    hash.hash(manifest.concat().as_bytes())
+                )?;
+            }
+            writeln!(out)?;
         }
-        let name = String::from_utf8_lossy(member.name());
+        ListFormat::Json => {
+            if !first {
+                writeln!(out)?;
+            }
+            writeln!(out, "]")?;
+        }
+        ListFormat::Csv => {}
+    }
+    // Flush before reporting duplicates so stderr isn't interleaved with the listing.
+    out.flush()?;
 
-        let new_member = ar_archive_writer::NewArchiveMember {
-            buf: Box::new(data),
-            object_reader: &ar_archive_writer::DEFAULT_OBJECT_READER,
-            member_name: name.into(),
-            mtime: member.date().unwrap_or(0),
-            uid: member.uid().unwrap_or(0) as u32,
-            gid: member.gid().unwrap_or(0) as u32,
-            perms: member.mode().unwrap_or(0o644) as u32,
-        };
-        if exclude {
-            if extracted_lib.is_some() {
-                extracted_members.push(new_member);
+    let mut duplicates = false;
+    for (symbol, offsets) in definitions.iter().filter(|(_, offsets)| offsets.len() > 1) {
+        let offsets: Vec<_> = offsets.iter().map(|offset| format!("{offset:#X}")).collect();
+        if !options.quiet {
+            eprintln!("duplicate symbol {symbol} defined by members at {}", offsets.join(", "));
+        }
+        duplicates = true;
+    }
+
+    Ok(if duplicates { ExitCode::FAILURE } else { ExitCode::SUCCESS })
+}
+
+/// Call `print` with a buffered stdout and flush it afterwards.
+///
+/// Unlike `print!`, this returns an error instead of panicking if stdout is
+/// closed, such as a pipe whose reader has exited.
+fn with_stdout<T>(
+    print: impl FnOnce(&mut BufWriter<io::StdoutLock<'static>>) -> io::Result<T>,
+) -> Result<T, WinlibError> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    print(&mut out)
+        .and_then(|value| out.flush().map(|()| value))
+        .map_err(|cause| WinlibError::IoError { msg: "unable to write to stdout".into(), cause })
+}
+
+/// Print the changes returned by [`diff_libs`].
+fn print_diff(out: &mut impl Write, changes: &[SymbolChange]) -> io::Result<()> {
+    for change in changes {
+        match change {
+            SymbolChange::Removed(name) => writeln!(out, "- {name}")?,
+            SymbolChange::Added(name) => writeln!(out, "+ {name}")?,
+            SymbolChange::DllChanged { name, old_dll, new_dll } => {
+                let old_dll = old_dll.as_deref().unwrap_or("(none)");
+                let new_dll = new_dll.as_deref().unwrap_or("(none)");
+                writeln!(out, "~ {name}: {old_dll} -> {new_dll}")?
             }
-        } else {
-            included_members.push(new_member);
         }
     }
+    Ok(())
+}
 
-    let mut writer = Cursor::new(Vec::with_capacity(64 * 1024));
-    if let Some(lib) = extracted_lib {
-        ar_archive_writer::write_archive_to_stream(
-            &mut writer,
-            &extracted_members,
-            ar_archive_writer::ArchiveKind::Coff,
-            false,
-            false,
-        )
-        .map_err(|e| WinlibError::IoError {
-            msg: "could not create new library file".into(),
-            cause: e,
-        })?;
-        fs::write(lib, writer.get_ref()).map_err(|e| WinlibError::IoError {
-            msg: format!("unable to write library to {}", lib.display()),
-            cause: e,
-        })?;
+/// A symbol name followed by its demangled form in parentheses, if `demangle`
+/// is set and it's an MSVC C++ name.
+fn show_symbol(symbol: &str, demangle: bool) -> Cow<'_, str> {
+    match demangle.then(|| winlib::demangle(symbol)).flatten() {
+        Some(demangled) => format!("{symbol} ({demangled})").into(),
+        None => symbol.into(),
+    }
+}
+
+/// Print the symbols returned by [`symbols_by_member`], prefixed by the name of their member.
+fn print_symbols(
+    out: &mut impl Write,
+    members: &[MemberSymbols],
+    color: bool,
+    demangle: bool,
+) -> io::Result<()> {
+    for member in members {
+        let name = paint(&member.name, kind_sgr(member.kind), color);
+        for symbol in &member.symbols {
+            let symbol_name = show_symbol(symbol.name(), demangle);
+            match symbol {
+                MemberSymbol::Weak { target, .. } => {
+                    writeln!(out, "{name}: weak {symbol_name} -> {}", show_symbol(target, demangle))
+                }
+                _ => match symbol.ordinal() {
+                    Some(ordinal) => writeln!(out, "{name}: {symbol_name} @{ordinal} NONAME"),
+                    None => writeln!(out, "{name}: {symbol_name}"),
+                },
+            }?;
+        }
     }
+    Ok(())
+}
 
-    let mut writer = writer.into_inner();
-    writer.truncate(0);
-    let mut writer = Cursor::new(writer);
-    ar_archive_writer::write_archive_to_stream(
-        &mut writer,
-        &included_members,
-        ar_archive_writer::ArchiveKind::Coff,
-        false,
-        false,
-    )
-    .map_err(|e| WinlibError::IoError {
-        msg: "could not create new library file".into(),
-        cause: e,
-    })?;
-    fs::write(out_lib, writer.get_ref()).map_err(|e| WinlibError::IoError {
-        msg: format!("unable to write library to {}", out_lib.display()),
-        cause: e,
-    })?;
+/// Print each symbol returned by [`undefined_symbols`] followed by the members that reference it.
+fn print_undefined(
+    out: &mut impl Write,
+    references: &[SymbolReferences],
+    color: bool,
+    demangle: bool,
+) -> io::Result<()> {
+    for SymbolReferences { symbol, members } in references {
+        let members: Vec<_> =
+            members.iter().map(|(name, kind)| paint(name, kind_sgr(*kind), color)).collect();
+        writeln!(out, "{}: {}", show_symbol(symbol, demangle), members.join(", "))?;
+    }
+    Ok(())
+}
 
+/// Print the imports returned by [`lib_imports`], prefixed by the name of their member.
+fn print_imports(out: &mut impl Write, members: &[MemberImports], color: bool) -> io::Result<()> {
+    for member in members {
+        let name = paint(&member.name, kind_sgr(MemberKind::ImportObject), color);
+        for import in &member.imports {
+            writeln!(out, "{name}: {import}")?;
+        }
+    }
     Ok(())
 }
 
-fn list_lib(lib_path: &OsStr) -> Result<(), WinlibError> {
-    let data = fs::read(lib_path).map_err(|e| WinlibError::IoError {
-        msg: format!("cannot read {}", lib_path.display()),
+/// Print the entries returned by [`symbol_index`] with the offset and name of
+/// the member that each points to.
+///
+/// Entries that don't point to the start of a member are reported to stderr
+/// and make this return false.
+fn print_symbol_index(out: &mut impl Write, entries: &[IndexEntry]) -> io::Result<bool> {
+    let mut valid = true;
+    for entry in entries {
+        let name = &entry.name;
+        // The index points to member headers whereas `list` shows the offset of the data.
+        match &entry.member {
+            Ok((offset, member)) => writeln!(out, "{offset:>#10X}  {member}: {name}")?,
+            Err(e) => {
+                out.flush()?;
+                eprintln!(
+                    "corrupt symbol index: {name} points to {:#X}, which isn't a member\ncause: {e}",
+                    entry.header_offset
+                );
+                valid = false;
+            }
+        }
+    }
+    Ok(valid)
+}
+
+/// Print the long name table returned by [`name_table`] as a mapping from
+/// offsets to names, followed by the name reference in the header of each member.
+///
+/// Members whose name fits in the header are shown as `inline`.
+fn print_name_table(out: &mut impl Write, table: &NameTable) -> io::Result<()> {
+    match table.table {
+        Some((offset, size)) => writeln!(out, "name table: {size:#X} bytes at {offset:#X}")?,
+        None => writeln!(out, "name table: none")?,
+    }
+    for (start, name) in &table.names {
+        // Headers refer to names by their decimal offset so show them the same way.
+        let reference = format!("/{start}");
+        writeln!(out, "{reference:>10}  {name}")?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:>10}  {:>10}  member name", "offset", "reference")?;
+    for (offset, reference, name) in &table.members {
+        let reference = reference.as_deref().unwrap_or("inline");
+        writeln!(out, "{offset:>#10X}  {reference:>10}  {name}")?;
+    }
+    Ok(())
+}
+
+/// Print the DLLs returned by [`lib_dlls`] along with the number of symbols imported from each.
+fn print_dlls(out: &mut impl Write, dlls: &[DllImports]) -> io::Result<()> {
+    for DllImports { name, symbols, ordinals } in dlls {
+        let plural = if *symbols == 1 { "" } else { "s" };
+        if *ordinals > 0 {
+            writeln!(out, "{name}: {symbols} symbol{plural} ({ordinals} by ordinal)")?;
+        } else {
+            writeln!(out, "{name}: {symbols} symbol{plural}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the mismatches returned by [`verify_symbol_index`].
+///
+/// Entries that point to a member that doesn't define the symbol are shown as
+/// `stale` and symbols that are defined by a member but aren't in the index
+/// are shown as `missing`.
+fn print_index_mismatches(out: &mut impl Write, mismatches: &[IndexMismatch]) -> io::Result<()> {
+    for mismatch in mismatches {
+        match mismatch {
+            IndexMismatch::NotAMember { name, header_offset } => {
+                writeln!(out, "stale: {name} points to {header_offset:#X}, which isn't a member")?
+            }
+            IndexMismatch::Missing { name, member, offset } => {
+                writeln!(out, "missing: {name} defined by {member} at {offset:#X}")?
+            }
+            IndexMismatch::Stale { name, offset } => {
+                writeln!(out, "stale: {name} points to {offset:#X}, which doesn't define it")?
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether `error` is from writing to a pipe whose reader has exited, such as
+/// `head`. Stopping early because of that isn't a failure.
+fn is_broken_pipe(error: &WinlibError) -> bool {
+    matches!(error, WinlibError::IoError { cause, .. } if cause.kind() == io::ErrorKind::BrokenPipe)
+}
+
+/// Print an error to stderr in the format chosen by `--error-format` and return
+/// the exit code for its category. A broken pipe is silently treated as success.
+fn report_error(error: &WinlibError, json: bool) -> ExitCode {
+    if is_broken_pipe(error) {
+        return ExitCode::SUCCESS;
+    }
+    if json {
+        let cause = error.source().map_or("null".into(), |cause| json_string(&cause.to_string()));
+        eprintln!(
+            r#"{{"code": {}, "message": {}, "cause": {cause}}}"#,
+            json_string(error.code()),
+            json_string(error.message())
+        );
+    } else {
+        eprintln!("error: {error}");
+    }
+    ExitCode::from(error.exit_code())
+}
+
+/// Write `text` to stdout.
+///
+/// Unlike `print!`, this returns an error instead of panicking if stdout is
+/// closed, such as a pipe whose reader has exited.
+fn write_stdout(text: &str) -> Result<(), WinlibError> {
+    let mut out = io::stdout().lock();
+    out.write_all(text.as_bytes())
+        .and_then(|()| out.flush())
+        .map_err(|cause| WinlibError::IoError { msg: "unable to write to stdout".into(), cause })
+}
+
+/// The machine type for a `--machine` value.
+fn machine_type(s: &str) -> MachineTypes {
+    match s {
+        "x86" => MachineTypes::I386,
+        "arm64" => MachineTypes::ARM64,
+        "arm64ec" => MachineTypes::ARM64EC,
+        "arm" => MachineTypes::ARMNT,
+        _ => MachineTypes::AMD64,
+    }
+}
+
+/// The import type for an `--import-type` value.
+fn import_kind(cfg: &clap::ArgMatches) -> Option<ImportKind> {
+    cfg.get_one::<String>("import-type").map(|s| match s.as_str() {
+        "data" => ImportKind::Data,
+        "const" => ImportKind::Const,
+        _ => ImportKind::Code,
+    })
+}
+
+/// The format for an `--archive-kind` value. Without it, the old lib's format is kept.
+fn archive_format(cfg: &ArgMatches) -> Option<ArchiveFormat> {
+    cfg.get_one::<String>("archive-kind").map(|s| match s.as_str() {
+        "gnu" => ArchiveFormat::Gnu,
+        _ => ArchiveFormat::Coff,
+    })
+}
+
+/// The member kind for an `--only` value, which is one of the names used by `list --type`.
+fn only_kind(cfg: &ArgMatches) -> Option<MemberKind> {
+    cfg.get_one::<String>("only").map(|s| match s.as_str() {
+        "import" => MemberKind::Import,
+        "import-object" => MemberKind::ImportObject,
+        "delay-import-object" => MemberKind::DelayImportObject,
+        "object" => MemberKind::Object,
+        _ => MemberKind::Unknown,
+    })
+}
+
+/// Work out whether output should be colored from the `--color` option.
+///
+/// `auto` colors output if stdout is a terminal and `NO_COLOR` isn't set.
+fn use_color(cfg: &ArgMatches) -> bool {
+    match cfg.get_one::<String>("color").map(String::as_str) {
+        Some("always") => true,
+        Some("never") => false,
+        _ => {
+            std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                && io::stdout().is_terminal()
+        }
+    }
+}
+
+/// Parse a decimal or `0x` prefixed hexadecimal value, in the format `list` prints offsets.
+fn hex_value(s: &str) -> Result<u64, ParseIntError> {
+    let s = s.trim();
+    let offset = if let Some(s) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(s, 16)
+    } else {
+        u64::from_str_radix(s, 10)
+    }?;
+    Ok(offset)
+}
+
+/// The machine type for a `--target` triple.
+fn target(s: &str) -> Result<u16, String> {
+    target_machine(s).ok_or_else(|| format!("`{s}` isn't a known Windows target triple"))
+}
+
+fn offset_range(s: &str) -> Result<RangeInclusive<u64>, String> {
+    match s.split_once('-') {
+        Some((start, end)) => {
+            let start = hex_value(start.trim()).map_err(|e| e.to_string())?;
+            let end = hex_value(end.trim()).map_err(|e| e.to_string())?;
+            if start > end {
+                return Err(format!("the range start {start:#x} is after its end {end:#x}"));
+            }
+            Ok(start..=end)
+        }
+        None => hex_value(s.trim()).map(|offset| offset..=offset).map_err(|e| e.to_string()),
+    }
+}
+
+/// Returns true for the lines of `winlib list` output that don't describe a
+/// member, such as the column headings, the summary and the symbols and
+/// sections shown under each member.
+fn is_list_decoration(line: &str) -> bool {
+    let trimmed = line.trim();
+    let is_header = trimmed.starts_with("offset") && trimmed.ends_with("member name");
+    let is_summary = trimmed == "(no members)"
+        || trimmed.split_once(' ').is_some_and(|(count, rest)| {
+            count.bytes().all(|b| b.is_ascii_digit())
+                && (rest.starts_with("member, total ") || rest.starts_with("members, total "))
+        });
+    // The `--group-by-dll` headings, such as `kernel32.dll:` or `(objects):`.
+    let is_group = trimmed.ends_with(':') && !trimmed.contains(char::is_whitespace);
+    // Member rows start with the offset, right-aligned in a narrower column.
+    let is_detail = line.len() - line.trim_start().len() >= 24;
+    is_header || is_summary || is_group || is_detail
+}
+
+/// Read member offsets from a file with one offset or range per line.
+///
+/// Anything after a `#` is a comment and blank lines are ignored. Only the
+/// first field of each line is used and the other lines of `winlib list`
+/// output are skipped, so its output can be fed back in directly.
+fn read_offsets_file(path: &OsStr) -> Result<Vec<RangeInclusive<u64>>, WinlibError> {
+    let data = fs::read(path).map_err(|e| WinlibError::IoError {
+        msg: format!("cannot read {}", path.display()),
         cause: e,
     })?;
-    let archive = ArchiveFile::parse(&*data).map_err(|e| WinlibError::ObjectError {
-        msg: format!("not a recognised archive file: {}", lib_path.display()),
+    let text = String::from_utf8_lossy(&data);
+    let mut offsets = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        if is_list_decoration(line) {
+            continue;
+        }
+        let line = line.split('#').next().unwrap_or_default();
+        let Some(field) = line.split_whitespace().next() else { continue };
+        let range = offset_range(field).map_err(|e| WinlibError::InvalidInput {
+            msg: format!("{}:{}: invalid offset `{field}`: {e}", path.display(), line_number + 1),
+        })?;
+        offsets.push(range);
+    }
+    Ok(offsets)
+}
+
+/// Read a list of members from a file with one name or offset per line.
+///
+/// Blank lines and `#` comments are ignored. A line whose first field is an
+/// offset refers to that member and the other lines of `winlib list` output
+/// are skipped, so its output can be used. Otherwise the whole line is a
+/// member name.
+fn read_member_list(path: &OsStr) -> Result<Vec<MemberRef>, WinlibError> {
+    let data = fs::read(path).map_err(|e| WinlibError::IoError {
+        msg: format!("cannot read {}", path.display()),
         cause: e,
     })?;
+    let text = String::from_utf8_lossy(&data);
+    let mut members = Vec::new();
+    for line in text.lines() {
+        if is_list_decoration(line) {
+            continue;
+        }
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some(field) = line.split_whitespace().next() else { continue };
+        members.push(match hex_value(field) {
+            Ok(offset) => MemberRef::Offset(offset),
+            Err(_) => MemberRef::Name(line.into()),
+        });
+    }
+    Ok(members)
+}
 
-    println!("{:>10}  {:>10}  member name", "offset", "size");
-    for member in archive.members() {
-        let member = member.map_err(|e| WinlibError::ObjectError {
-            msg: format!("could not read archive member in {}", lib_path.display()),
-            cause: e,
-        })?;
-        let name = String::from_utf8_lossy(member.name());
-        let (offset, size) = member.file_range();
-        println!("{offset:>#10X}  {size:>#10X}  {name}");
+fn dll_rename(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((old, new)) if !old.is_empty() && !new.is_empty() => Ok((old.into(), new.into())),
+        _ => Err("expected `<OLD>=<NEW>`".into()),
     }
+}
 
-    Ok(())
+/// Get the patterns given for the argument `id`.
+///
+/// These are regular expressions if `--regex` was given, otherwise they're
+/// wildcard patterns. Exits with an argument error if a regular expression is invalid.
+fn patterns(cfg: &ArgMatches, id: &str) -> Vec<Pattern> {
+    let regex = cfg.get_flag("regex");
+    let patterns = cfg.get_many::<String>(id).unwrap_or_default();
+    patterns
+        .map(|pattern| {
+            if !regex {
+                return Pattern::Glob(pattern.clone());
+            }
+            Pattern::regex(pattern).unwrap_or_else(|e| {
+                let msg = format!("invalid regular expression '{pattern}' for '--{id}': {e}\n");
+                clap::Error::raw(ErrorKind::ValueValidation, msg).exit()
+            })
+        })
+        .collect()
+}
+
+/// The default path for `create`'s output: `foo.lib` becomes `foo.trimmed.lib`.
+fn trimmed_lib_path(from_lib: &OsStr) -> OsString {
+    if from_lib == "-" {
+        return from_lib.to_owned();
+    }
+    let path = Path::new(from_lib);
+    let mut file_name = path.file_stem().unwrap_or_default().to_owned();
+    file_name.push(".trimmed");
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name).into_os_string()
 }
 
 fn main() -> ExitCode {
     use clap::{arg, builder::ValueParser};
-    fn hex_value(s: &str) -> Result<u32, ParseIntError> {
-        let offset = if let Some(s) = s.strip_prefix("0x") {
-            u32::from_str_radix(s, 16)
-        } else {
-            u32::from_str_radix(s, 10)
-        }?;
-        Ok(offset)
-    }
     let matches = clap::Command::new("winlib")
         .version("0.2.1")
         .propagate_version(true)
         .subcommand_required(true)
         .arg_required_else_help(true)
+        .arg(arg!(-q --quiet "Don't print warnings, progress, `diff` differences or informational messages. Errors are still printed.").global(true))
+        .arg(arg!(--"error-format" <FORMAT> "The format of error messages written to stderr.").value_parser(["text", "json"]).default_value("text").global(true))
         .subcommand(clap::Command::new("list")
             .about("Show the contents of a lib.")
-            .arg(arg!([LIB_PATH] "the path of the lib to inspect").value_parser(ValueParser::os_string())))
+            .arg(arg!([LIB_PATH] "the path of the lib to inspect").value_parser(ValueParser::os_string()))
+            .arg(arg!(--format <FORMAT> "The output format.").value_parser(["table", "json", "csv"]).default_value("table"))
+            .arg(arg!(--filter <GLOB> "Only show members whose name matches the wildcard pattern <GLOB>."))
+            .arg(arg!(--regex "Treat --filter as a regular expression that can match any part of a member name. Use `^` and `$` to match the whole name."))
+            .arg(arg!(--symbols "Show the symbols provided by each member."))
+            .arg(arg!(--sections "Show the name and size of each section in each member."))
+            .arg(arg!(--dll "Show the DLL that each import member imports from."))
+            .arg(arg!(--arch "Show the machine type of each member."))
+            .arg(arg!(--type "Show whether each member is an import, an import object, a delay-load import object or a plain object."))
+            .arg(arg!(--"only-delayload" "Only show delay-load import objects."))
+            .arg(arg!(--only <KIND> "Only show members of this kind, as shown by --type.").value_parser(["import", "import-object", "delay-import-object", "object", "unknown"]))
+            .arg(arg!(--"import-type" <TYPE> "Only show import members that import this type of symbol.").value_parser(["code", "data", "const"]))
+            .arg(arg!(--count "Only print the number of members that would be shown.").conflicts_with_all(["format", "check-duplicates"]))
+            .arg(arg!(-v --verbose "Show the modification time, user id, group id and mode of each member."))
+            .arg(arg!(--"check-duplicates" "Report symbols that are defined by more than one member and fail if there are any. Weak externals aren't counted."))
+            .arg(arg!(--color <WHEN> "Color the table output.").value_parser(["auto", "always", "never"]).default_value("auto"))
+            .arg(arg!(--hash <ALGORITHM> "Show a hash of each member's data, and of the sorted list of member hashes and names in the summary.").value_parser(["sha256", "crc32"]))
+            .arg(arg!(--"group-by-dll" "Group import members under the DLL they import from, followed by all other members under `(objects)`.").conflicts_with_all(["format", "count", "name-table"]))
+            .arg(arg!(--"max-member-size" <BYTES> "Fail if a member is larger than <BYTES>, which can be decimal or `0x` prefixed hexadecimal.").value_parser(hex_value))
+            .arg(arg!(--stats "Print the time taken, the number of members and the number of bytes read to stderr."))
+            .arg(arg!(--"name-table" "Print the long name table as a mapping from offsets to names, and the name reference of each member, instead.").conflicts_with_all(["format", "filter", "count", "check-duplicates"])))
         .subcommand(
             clap::Command::new("create")
                 .about("Create a new lib from an old lib.")
-                .arg(arg!(<LIB_PATH> "the new path of the lib to create").required(true).value_parser(ValueParser::os_string()))
-                .arg(arg!(--from <PATH> "The new lib will contain members from the old lib at <PATH>.").required(true).value_parser(ValueParser::os_string()))
-                // FIXME: use a custom value parser
-                .arg(arg!(--exclude <OFFSET> "Exclude the member at the given offset.").value_parser(hex_value).action(ArgAction::Append))
-                .arg(arg!(--"exclude-idata" "Exclude members containing .idata sections."))
+                .arg(arg!([LIB_PATH] "the new path of the lib to create. Defaults to the old lib's path with `.trimmed` before the extension").value_parser(ValueParser::os_string()))
+                .arg(arg!(--from <PATH> "The new lib will contain members from the old lib at <PATH>. This can be given multiple times and the file name can use `*` and `?` wildcards.").required(true).value_parser(ValueParser::os_string()).action(ArgAction::Append))
+                .arg(arg!(--"in-place" "Replace the old lib with the new lib.").conflicts_with("LIB_PATH"))
+                .arg(arg!(--append "Keep the members of the existing lib at LIB_PATH and add the included members after them.").conflicts_with("in-place"))
+                .arg(arg!(--"allow-duplicates" "With --append, keep new members even if the existing lib has a member with the same name.").requires("append"))
+                .arg(arg!(--include <PATTERN> "Only include members whose name matches the wildcard pattern <PATTERN>. Exclusions still apply to these members.").action(ArgAction::Append))
+                .arg(arg!(--"members-from" <PATH> "Only include the members listed in <PATH>, one name or offset per line, in that order. Fails if one of them isn't found.").value_parser(ValueParser::os_string()).conflicts_with("sort"))
+                .arg(arg!(--exclude <OFFSET> "Exclude the member at the given offset. This can be a comma separated list of offsets or inclusive ranges such as `0x10-0x80`.").value_parser(offset_range).value_delimiter(',').action(ArgAction::Append))
+                .arg(arg!(--"exclude-file" <PATH> "Exclude the members at the offsets listed in <PATH>, one per line. Blank lines, `#` comments and the other lines of `list` output are ignored.").value_parser(ValueParser::os_string()).action(ArgAction::Append))
+                .arg(arg!(--strict "Fail if an offset given to --exclude or --exclude-file doesn't match a member, or if a member doesn't match --target, instead of warning."))
+                .arg(arg!(--"exclude-idata" "Exclude members containing .idata or .didat sections."))
+                .arg(arg!(--"skip-unrecognized" "With --exclude-idata, warn about and keep members that can't be recognised instead of failing."))
+                .arg(arg!(-j --jobs <N> "The number of threads to use for --exclude-idata. Defaults to the number of available CPUs.").value_parser(clap::value_parser!(usize)))
+                .arg(arg!(--"exclude-symbol" <NAME> "Exclude members that define or import the symbol <NAME>. A trailing `*` matches any suffix.").action(ArgAction::Append))
+                .arg(arg!(--"exclude-dll" <NAME> "Exclude import members that import from the DLL <NAME>.").action(ArgAction::Append))
+                .arg(arg!(--"exclude-section" <PREFIX> "Exclude objects with a section whose name starts with <PREFIX>, such as `.debug`.").action(ArgAction::Append))
+                .arg(arg!(--"max-member-size" <BYTES> "Fail if a member of an old lib is larger than <BYTES>, which can be decimal or `0x` prefixed hexadecimal.").value_parser(hex_value))
+                .arg(arg!(--"exclude-empty" "Exclude objects that don't define any symbols other than weak externals."))
+                .arg(arg!(--"rename-dll" <RENAME> "Make import members that import from the DLL <OLD> import from <NEW> instead, where <RENAME> is `<OLD>=<NEW>`. The import descriptor and null thunk objects for <OLD> are replaced with ones for <NEW>.").value_parser(dll_rename).action(ArgAction::Append))
+                .arg(arg!(--machine <MACHINE> "Only include members for the target machine. Members that aren't specific to a machine are always included.").value_parser(["x64", "x86", "arm64", "arm64ec", "arm"]))
+                .arg(arg!(--"drop-unparseable" "With --machine, exclude members whose machine type can't be read instead of including them.").requires("machine"))
+                .arg(arg!(--target <TRIPLE> "Warn about included members for a different machine than the target triple, such as `x86_64-pc-windows-msvc`. Fails with --strict.").value_parser(target))
+                .arg(arg!(--"import-type" <TYPE> "Exclude import members that don't import this type of symbol. Other members are kept.").value_parser(["code", "data", "const"]))
+                .arg(arg!(--"strip-section" <NAME> "Remove sections named <NAME> from object members. A trailing `*` matches any suffix.").action(ArgAction::Append))
                 .arg(arg!(--"save-excluded" <PATH> "Store the excluded members in a separate library at <PATH>.").value_parser(ValueParser::os_string()))
+                .arg(arg!(--"output-manifest" <PATH> "Write a JSON manifest of the included members, the excluded members and why they were excluded, and the members of the new lib to <PATH>.").value_parser(ValueParser::os_string()))
+                .arg(arg!(--"symbol-index" "Write a symbol index listing the symbols defined by each member. This is the default.").overrides_with("no-symbol-index"))
+                .arg(arg!(--"no-symbol-index" "Write an empty symbol index.").overrides_with("symbol-index"))
+                .arg(arg!(--"archive-kind" <KIND> "The archive format to write. Defaults to the format of the old lib.").value_parser(["coff", "gnu"]))
+                .arg(arg!(--"also-gnu" <PATH> "Also write the included members to a GNU format lib at <PATH>. The main lib must be COFF.").value_parser(ValueParser::os_string()))
+                .arg(arg!(--"write-thin" "Write a thin lib that refers to the files of the members of thin old libs instead of containing their data. The new lib is only valid while those files stay in the same place relative to it.").conflicts_with_all(["append", "also-gnu", "archive-kind", "preserve-metadata"]))
+                .arg(arg!(--"preserve-metadata" "Leave the modification time, user id, group id and mode of members blank if they're blank in the old lib, instead of writing a default value.").conflicts_with("deterministic"))
+                .arg(arg!(--deterministic "Zero the timestamp, user id and group id of every member and set its mode to 644."))
+                .arg(arg!(--sort "Sort the members by name. This can change which member the linker uses for a symbol that's defined more than once. The --save-excluded lib is sorted too."))
+                .arg(arg!(--dedupe "Drop members with the same name and data as a member that's already included."))
+                .arg(arg!(--progress "Print how many members have been processed to stderr."))
+                .arg(arg!(--stats "Print the time taken, the number of members and the number of bytes read to stderr, and the time spent parsing members for --exclude-idata."))
+                .arg(arg!(-f --force "Overwrite the output libs if they already exist, even if one of them is the old lib."))
+                .arg(arg!(--regex "Treat --include, --exclude-symbol, --exclude-dll and --strip-section as regular expressions that can match any part of a name. Use `^` and `$` to match the whole name."))
+                .arg(arg!(--"dry-run" "Show which members would be included or excluded without writing anything."))
+        )
+        .subcommand(
+            clap::Command::new("remove")
+                .about("Create a copy of a lib with some members removed.")
+                .arg(arg!(<LIB_PATH> "the path of the lib to remove members from").value_parser(ValueParser::os_string()))
+                .arg(arg!(--out <PATH> "The path of the new lib.").required(true).value_parser(ValueParser::os_string()))
+                .arg(arg!(--name <PATTERN> "Remove members whose name matches the wildcard pattern <PATTERN>.").required(true).action(ArgAction::Append))
+                .arg(arg!(--regex "Treat --name as a regular expression that can match any part of a member name. Use `^` and `$` to match the whole name."))
+                .arg(arg!(-f --force "Overwrite the new lib if it already exists, even if it's the old lib."))
+        )
+        .subcommand(
+            clap::Command::new("merge")
+                .about("Combine the members of several libs into one lib.")
+                .arg(arg!(<LIBS> ... "the paths of the libs to merge").value_parser(ValueParser::os_string()))
+                .arg(arg!(--out <PATH> "The path of the new lib.").required(true).value_parser(ValueParser::os_string()))
+                .arg(arg!(--"allow-duplicates" "Keep members even if a member with the same name is in another lib."))
+                .arg(arg!(--dedupe "Drop members with the same name and data as a member that's already included. These aren't treated as duplicates."))
+                .arg(arg!(--sort "Sort the members by name. This can change which member the linker uses for a symbol that's defined more than once."))
+                .arg(arg!(--"archive-kind" <KIND> "The archive format to write. Defaults to the format of the first lib.").value_parser(["coff", "gnu"]))
+                .arg(arg!(-f --force "Overwrite the new lib if it already exists, even if it's one of the libs being merged."))
+        )
+        .subcommand(
+            clap::Command::new("add")
+                .about("Add object files to an existing lib.")
+                .arg(arg!(<LIB_PATH> "the path of the lib to add to").value_parser(ValueParser::os_string()))
+                .arg(arg!(<OBJECTS> ... "the paths of the object files to add").value_parser(ValueParser::os_string()))
+                .arg(arg!(--as <NAME> "The member name to give the object instead of its file name. Only valid with a single object."))
+                .arg(arg!(--mixed "Add objects even if their machine type doesn't match the lib's members."))
+                .arg(arg!(--"allow-duplicates" "Add objects even if the lib already has a member with the same name."))
+        )
+        .subcommand(
+            clap::Command::new("touch")
+                .about("Create a copy of a lib with the modification time of every member changed.")
+                .arg(arg!(<LIB_PATH> "the path of the lib to copy").value_parser(ValueParser::os_string()))
+                .arg(arg!(--out <PATH> "The path of the new lib.").required(true).value_parser(ValueParser::os_string()))
+                .arg(arg!(--mtime <EPOCH> "The modification time in seconds since the Unix epoch.").value_parser(clap::value_parser!(u64)).default_value("0"))
+                .arg(arg!(-f --force "Overwrite the new lib if it already exists, even if it's the old lib."))
+        )
+        .subcommand(
+            clap::Command::new("diff")
+                .about("Compare the symbols provided by two libs.")
+                .arg(arg!(<OLD> "the path of the old lib").value_parser(ValueParser::os_string()))
+                .arg(arg!(<NEW> "the path of the new lib").value_parser(ValueParser::os_string()))
+                .arg(arg!(--dlls "Also report symbols that are imported from a different DLL."))
+        )
+        .subcommand(
+            clap::Command::new("info")
+                .about("Show a summary of a lib.")
+                .arg(arg!(<LIB_PATH> "the path of the lib to summarise").value_parser(ValueParser::os_string()))
+        )
+        .subcommand(
+            clap::Command::new("verify")
+                .about("Check that every member of a lib can be parsed.")
+                .arg(arg!(<LIB_PATH> "the path of the lib to check").value_parser(ValueParser::os_string()))
+                .arg(arg!(--index "Check the symbol index against the symbols defined by each member instead."))
+                .arg(arg!(--target <TRIPLE> "Warn about members for a different machine than the target triple, such as `x86_64-pc-windows-msvc`.").value_parser(target).conflicts_with("index"))
+                .arg(arg!(--strict "With --target, fail instead of warning.").requires("target"))
+        )
+        .subcommand(
+            clap::Command::new("symbols")
+                .about("Show every symbol provided by a lib, along with the member that provides it.")
+                .arg(arg!(<LIB_PATH> "the path of the lib to inspect").value_parser(ValueParser::os_string()))
+                .arg(arg!(--undefined "Show each undefined symbol and the members that reference it instead."))
+                .arg(arg!(--index "Show the entries of the lib's symbol index, and the offset of the member each one points to, instead. Fails if an entry doesn't point to a member.").conflicts_with("undefined"))
+                .arg(arg!(--imports "Show the import descriptors and imported symbols in the .idata$ sections of import objects instead.").conflicts_with_all(["undefined", "index"]))
+                .arg(arg!(--color <WHEN> "Color member names.").value_parser(["auto", "always", "never"]).default_value("auto"))
+                .arg(arg!(--demangle "Follow MSVC C++ symbol names with their demangled form in parentheses.").conflicts_with_all(["index", "imports"]))
+        )
+        .subcommand(
+            clap::Command::new("dlls")
+                .about("Show the DLLs a lib imports from.")
+                .arg(arg!(<LIB_PATH> "the path of the lib to inspect").value_parser(ValueParser::os_string()))
+                .arg(arg!(--"import-type" <TYPE> "Only count symbols of this type.").value_parser(["code", "data", "const"]))
+        )
+        .subcommand(
+            clap::Command::new("def")
+                .about("Generate a module-definition (.def) file from an import lib.")
+                .arg(arg!(<LIB_PATH> "the path of the import lib").value_parser(ValueParser::os_string()))
+                .arg(arg!(--out <PATH> "Write the .def file to <PATH> instead of stdout.").value_parser(ValueParser::os_string()))
+                .arg(arg!(--undecorate "Export stdcall decorated x86 names such as `_Foo@4` as `Foo=_Foo@4`."))
+                .arg(arg!(-f --force "Overwrite the .def file given by --out if it already exists.").requires("out"))
+        )
+        .subcommand(
+            clap::Command::new("fromdef")
+                .about("Create an import lib from a module-definition (.def) file.")
+                .arg(arg!(<DEF_PATH> "the path of the .def file").value_parser(ValueParser::os_string()))
+                .arg(arg!(--out <PATH> "The path of the new lib.").required(true).value_parser(ValueParser::os_string()))
+                .arg(arg!(--dll <NAME> "The name of the DLL to import from. Defaults to the LIBRARY name in the .def file."))
+                .arg(arg!(--machine <MACHINE> "The target machine.").value_parser(["x64", "x86", "arm64", "arm64ec", "arm"]).default_value("x64"))
+                .arg(arg!(-f --force "Overwrite the new lib if it already exists."))
+        )
+        .subcommand(
+            clap::Command::new("extract")
+                .about("Extract members from a lib into files.")
+                .arg(arg!(<LIB_PATH> "the path of the lib to extract from").value_parser(ValueParser::os_string()))
+                .arg(arg!(--out <DIR> "The directory to write the members to.").value_parser(ValueParser::path_buf()).default_value("."))
+                .arg(arg!(--name <PATTERN> "Only extract members whose name matches the wildcard pattern <PATTERN>."))
+                .arg(arg!(--regex "Treat --name as a regular expression that can match any part of a member name. Use `^` and `$` to match the whole name."))
+        )
+        .subcommand(
+            clap::Command::new("split")
+                .about("Split a lib into one lib per imported DLL. Members that don't belong to a DLL are written to misc.lib, or misc-1.lib if misc.dll is imported.")
+                .arg(arg!(<LIB_PATH> "the path of the lib to split").value_parser(ValueParser::os_string()))
+                .arg(arg!(--"out-dir" <DIR> "The directory to write the libs to.").value_parser(ValueParser::path_buf()).default_value("."))
+                .arg(arg!(--"archive-kind" <KIND> "The archive format to write. Defaults to the format of the lib being split.").value_parser(["coff", "gnu"]))
+                .arg(arg!(-f --force "Overwrite the new libs if they already exist, even if one of them is the lib being split."))
         )
         .get_matches();
+    let quiet = matches.get_flag("quiet");
+    winlib::set_quiet(quiet);
+    let json_errors =
+        matches.get_one::<String>("error-format").is_some_and(|format| format == "json");
 
     match matches.subcommand() {
         Some(("create", cfg)) => {
-            let Some(target_lib) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
-            let Some(from_lib) = cfg.get_one::<OsString>("from") else { unreachable!() };
-            let exclude_offsets: Vec<u32> =
-                cfg.get_many("exclude").unwrap_or_default().copied().collect();
+            let from_libs: Vec<OsString> =
+                cfg.get_many("from").unwrap_or_default().cloned().collect();
+            let from_lib = &from_libs[0];
+            let in_place = cfg.get_flag("in-place");
+            if in_place && from_lib == "-" {
+                let msg = "--in-place can't be used when reading the old lib from stdin".into();
+                return report_error(&WinlibError::InvalidInput { msg }, json_errors);
+            }
+            // The default output path is only obvious for a single old lib.
+            let is_glob = |lib: &OsString| lib.to_string_lossy().contains(['*', '?']);
+            let target_lib = match cfg.get_one::<OsString>("LIB_PATH") {
+                Some(target_lib) => target_lib.clone(),
+                None if from_libs.len() > 1 || is_glob(from_lib) => {
+                    let msg = if in_place {
+                        "--in-place can only be used with a single old lib"
+                    } else {
+                        "the new lib's path must be given when there's more than one old lib"
+                    };
+                    let e = WinlibError::InvalidInput { msg: msg.into() };
+                    return report_error(&e, json_errors);
+                }
+                None if in_place => from_lib.clone(),
+                None => trimmed_lib_path(from_lib),
+            };
+            let mut exclude_offsets: Vec<RangeInclusive<u64>> =
+                cfg.get_many("exclude").unwrap_or_default().cloned().collect();
+            for path in cfg.get_many::<OsString>("exclude-file").unwrap_or_default() {
+                match read_offsets_file(path) {
+                    Ok(offsets) => exclude_offsets.extend(offsets),
+                    Err(e) => return report_error(&e, json_errors),
+                }
+            }
+            let member_list = match cfg.get_one::<OsString>("members-from") {
+                Some(path) => match read_member_list(path) {
+                    Ok(members) => Some(members),
+                    Err(e) => return report_error(&e, json_errors),
+                },
+                None => None,
+            };
             let exclude_idata = cfg.get_flag("exclude-idata");
+            let include_names = patterns(cfg, "include");
+            let exclude_symbols = patterns(cfg, "exclude-symbol");
+            let exclude_dlls = patterns(cfg, "exclude-dll");
             let save_excluded = cfg.get_one::<OsString>("save-excluded");
+            let jobs = cfg
+                .get_one::<usize>("jobs")
+                .copied()
+                .unwrap_or_else(|| thread::available_parallelism().map_or(1, |jobs| jobs.get()));
+            let archive_format = archive_format(cfg);
             let options = CreateOptions {
                 exclude_offsets,
                 exclude_idata,
+                include_names,
+                member_list,
+                exclude_symbols,
+                exclude_dlls,
+                exclude_sections: cfg
+                    .get_many::<String>("exclude-section")
+                    .unwrap_or_default()
+                    .cloned()
+                    .collect(),
                 save_excluded: save_excluded.cloned(),
+                write_thin: cfg.get_flag("write-thin"),
+                output_manifest: cfg.get_one::<OsString>("output-manifest").cloned(),
+                also_gnu: cfg.get_one::<OsString>("also-gnu").cloned(),
+                dry_run: cfg.get_flag("dry-run"),
+                skip_unrecognized: cfg.get_flag("skip-unrecognized"),
+                no_symbol_index: cfg.get_flag("no-symbol-index"),
+                deterministic: cfg.get_flag("deterministic"),
+                archive_format,
+                jobs,
+                force: cfg.get_flag("force"),
+                in_place,
+                progress: cfg.get_flag("progress"),
+                stats: cfg.get_flag("stats"),
+                sort: cfg.get_flag("sort"),
+                dedupe: cfg.get_flag("dedupe"),
+                exclude_empty: cfg.get_flag("exclude-empty"),
+                append: cfg.get_flag("append"),
+                allow_duplicates: cfg.get_flag("allow-duplicates"),
+                rename_dlls: cfg.get_many("rename-dll").unwrap_or_default().cloned().collect(),
+                machine: cfg.get_one::<String>("machine").map(|s| machine_type(s).into()),
+                drop_unparseable: cfg.get_flag("drop-unparseable"),
+                target: cfg.get_one::<u16>("target").copied(),
+                strict: cfg.get_flag("strict"),
+                preserve_metadata: cfg.get_flag("preserve-metadata"),
+                strip_sections: patterns(cfg, "strip-section"),
+                import_kind: import_kind(cfg),
+                max_member_size: cfg.get_one::<u64>("max-member-size").copied(),
+                ..Default::default()
             };
-            match create_lib(from_lib, target_lib, &options) {
+            match create_lib_from(&from_libs, &target_lib, &options) {
                 Ok(_) => return ExitCode::SUCCESS,
-                Err(e) => {
-                    eprintln!("error: {e}")
-                }
+                Err(e) => return report_error(&e, json_errors),
             }
         }
         Some(("list", cfg)) => {
             let Some(target_lib) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
-            match list_lib(target_lib) {
+            if cfg.get_flag("name-table") {
+                let table = name_table(target_lib);
+                return match table
+                    .and_then(|table| with_stdout(|out| print_name_table(out, &table)))
+                {
+                    Ok(()) => ExitCode::SUCCESS,
+                    Err(e) => report_error(&e, json_errors),
+                };
+            }
+            let format = match cfg.get_one::<String>("format").map(String::as_str) {
+                Some("json") => ListFormat::Json,
+                Some("csv") => ListFormat::Csv,
+                _ => ListFormat::Table,
+            };
+            let filter = patterns(cfg, "filter").pop();
+            let symbols = cfg.get_flag("symbols");
+            let sections = cfg.get_flag("sections");
+            let dll = cfg.get_flag("dll");
+            let arch = cfg.get_flag("arch");
+            let kind = cfg.get_flag("type");
+            let verbose = cfg.get_flag("verbose");
+            let check_duplicates = cfg.get_flag("check-duplicates");
+            let color = use_color(cfg);
+            let options = ListOptions {
+                format,
+                filter,
+                symbols,
+                sections,
+                dll,
+                arch,
+                kind,
+                verbose,
+                check_duplicates,
+                hash: cfg.get_one::<String>("hash").map(|hash| match hash.as_str() {
+                    "crc32" => HashKind::Crc32,
+                    _ => HashKind::Sha256,
+                }),
+                group_by_dll: cfg.get_flag("group-by-dll"),
+                color,
+                quiet,
+            };
+            let only_delayload = cfg.get_flag("only-delayload");
+            let fields = ListFields {
+                // The table colors member names by their kind.
+                kind: kind
+                    || (color && options.format == ListFormat::Table)
+                    || sections
+                    || only_delayload
+                    || only_kind(cfg).is_some(),
+                machine: arch,
+                import: dll || options.group_by_dll || import_kind(cfg).is_some(),
+                symbols: symbols || check_duplicates,
+                sections,
+                hash: options.hash,
+            };
+            let start = Instant::now();
+            match list_lib(
+                target_lib,
+                options.filter.as_ref(),
+                &fields,
+                cfg.get_one::<u64>("max-member-size").copied(),
+            ) {
+                Ok(mut members) => {
+                    let stats = cfg.get_flag("stats").then(|| Stats {
+                        elapsed: Duration::ZERO,
+                        members: members.len(),
+                        // Stdin can't be measured so count the member data instead.
+                        bytes: fs::metadata(target_lib).map_or_else(
+                            |_| members.iter().map(|member| member.size).sum(),
+                            |metadata| metadata.len(),
+                        ),
+                        parse_time: None,
+                    });
+                    let print_stats = || {
+                        if let Some(stats) = stats {
+                            Stats { elapsed: start.elapsed(), ..stats }.print();
+                        }
+                    };
+                    if only_delayload {
+                        members.retain(|member| member.kind == Some(MemberKind::DelayImportObject));
+                    }
+                    if let Some(kind) = only_kind(cfg) {
+                        members.retain(|member| member.kind == Some(kind));
+                    }
+                    if let Some(kind) = import_kind(cfg) {
+                        members.retain(|member| member.import_kind == Some(kind));
+                    }
+                    if cfg.get_flag("count") {
+                        if let Err(e) = write_stdout(&format!("{}\n", members.len())) {
+                            return report_error(&e, json_errors);
+                        }
+                        print_stats();
+                        return ExitCode::SUCCESS;
+                    }
+                    let mut out = BufWriter::new(io::stdout().lock());
+                    match print_members(&mut out, &members, &options) {
+                        Ok(code) => {
+                            drop(out);
+                            print_stats();
+                            return code;
+                        }
+                        Err(cause) => {
+                            let msg = "unable to write to stdout".into();
+                            return report_error(&WinlibError::IoError { msg, cause }, json_errors);
+                        }
+                    }
+                }
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("remove", cfg)) => {
+            let Some(lib_path) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
+            let Some(out_lib) = cfg.get_one::<OsString>("out") else { unreachable!() };
+            let options = CreateOptions {
+                exclude_names: patterns(cfg, "name"),
+                force: cfg.get_flag("force"),
+                ..Default::default()
+            };
+            match create_lib(lib_path, out_lib, &options) {
+                Ok(_) => return ExitCode::SUCCESS,
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("merge", cfg)) => {
+            let libs: Vec<OsString> = cfg.get_many("LIBS").unwrap_or_default().cloned().collect();
+            let Some(out_lib) = cfg.get_one::<OsString>("out") else { unreachable!() };
+            let options = MergeOptions {
+                allow_duplicates: cfg.get_flag("allow-duplicates"),
+                sort: cfg.get_flag("sort"),
+                dedupe: cfg.get_flag("dedupe"),
+                archive_format: archive_format(cfg),
+                force: cfg.get_flag("force"),
+            };
+            match merge_libs(&libs, out_lib, &options) {
+                Ok(_) => return ExitCode::SUCCESS,
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("add", cfg)) => {
+            let Some(lib_path) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
+            let objects: Vec<OsString> =
+                cfg.get_many("OBJECTS").unwrap_or_default().cloned().collect();
+            match add_objects(
+                lib_path,
+                &objects,
+                cfg.get_one::<String>("as").map(String::as_str),
+                cfg.get_flag("mixed"),
+                cfg.get_flag("allow-duplicates"),
+            ) {
                 Ok(_) => return ExitCode::SUCCESS,
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("touch", cfg)) => {
+            let Some(lib_path) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
+            let Some(out_lib) = cfg.get_one::<OsString>("out") else { unreachable!() };
+            let Some(&mtime) = cfg.get_one::<u64>("mtime") else { unreachable!() };
+            match touch_lib(lib_path, out_lib, mtime, cfg.get_flag("force")) {
+                Ok(_) => return ExitCode::SUCCESS,
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("diff", cfg)) => {
+            let Some(old_lib) = cfg.get_one::<OsString>("OLD") else { unreachable!() };
+            let Some(new_lib) = cfg.get_one::<OsString>("NEW") else { unreachable!() };
+            let changes = diff_libs(old_lib, new_lib, cfg.get_flag("dlls"));
+            let printed = changes.and_then(|changes| {
+                if !quiet {
+                    with_stdout(|out| print_diff(out, &changes))?;
+                }
+                Ok(changes.is_empty())
+            });
+            match printed {
+                Ok(true) => return ExitCode::SUCCESS,
+                Ok(false) => return ExitCode::FAILURE,
+                Err(e) if is_broken_pipe(&e) => return ExitCode::SUCCESS,
                 Err(e) => {
-                    eprintln!("error: {e}")
+                    // Like diff(1), errors exit with 2 so they can't be mistaken for differences.
+                    report_error(&e, json_errors);
+                    return ExitCode::from(2);
+                }
+            }
+        }
+        Some(("info", cfg)) => {
+            let Some(lib_path) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
+            match lib_info(lib_path) {
+                Ok(info) => {
+                    let list = |items: Vec<String>| {
+                        if items.is_empty() { "-".into() } else { items.join(", ") }
+                    };
+                    let index = match info.index_symbols {
+                        Some(count) => {
+                            let plural = if count == 1 { "" } else { "s" };
+                            format!("{count} symbol{plural}")
+                        }
+                        None => "none".into(),
+                    };
+                    let machines =
+                        info.machines.iter().map(|&machine| machine_name(machine).into()).collect();
+                    let text = format!(
+                        "kind: {}\nmembers: {}\nsymbol index: {index}\nmachines: {}\ndlls: {}\nsize: {:#X} bytes\n",
+                        info.kind,
+                        info.members,
+                        list(machines),
+                        list(info.dlls.into_iter().collect()),
+                        info.size
+                    );
+                    if let Err(e) = write_stdout(&text) {
+                        return report_error(&e, json_errors);
+                    }
+                    return ExitCode::SUCCESS;
+                }
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("verify", cfg)) => {
+            let Some(lib_path) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
+            if cfg.get_flag("index") {
+                let mismatches = verify_symbol_index(lib_path).and_then(|mismatches| {
+                    with_stdout(|out| print_index_mismatches(out, &mismatches))?;
+                    Ok(mismatches)
+                });
+                match mismatches {
+                    Ok(mismatches) if mismatches.is_empty() => return ExitCode::SUCCESS,
+                    Ok(_) => return ExitCode::FAILURE,
+                    Err(e) => return report_error(&e, json_errors),
+                }
+            }
+            let target = cfg.get_one::<u16>("target").copied();
+            match verify_lib(lib_path, target, cfg.get_flag("strict")) {
+                Ok(count) => {
+                    if !quiet {
+                        let plural = if count == 1 { "" } else { "s" };
+                        if let Err(e) = write_stdout(&format!("{count} member{plural} verified\n"))
+                        {
+                            return report_error(&e, json_errors);
+                        }
+                    }
+                    return ExitCode::SUCCESS;
+                }
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("symbols", cfg)) => {
+            let Some(lib_path) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
+            if cfg.get_flag("index") {
+                let entries = symbol_index(lib_path);
+                match entries
+                    .and_then(|entries| with_stdout(|out| print_symbol_index(out, &entries)))
+                {
+                    Ok(true) => return ExitCode::SUCCESS,
+                    Ok(false) => return ExitCode::FAILURE,
+                    Err(e) => return report_error(&e, json_errors),
+                }
+            }
+            if cfg.get_flag("imports") {
+                let color = use_color(cfg);
+                let imports = lib_imports(lib_path);
+                match imports
+                    .and_then(|imports| with_stdout(|out| print_imports(out, &imports, color)))
+                {
+                    Ok(_) => return ExitCode::SUCCESS,
+                    Err(e) => return report_error(&e, json_errors),
+                }
+            }
+            let (color, demangle) = (use_color(cfg), cfg.get_flag("demangle"));
+            let printed = if cfg.get_flag("undefined") {
+                undefined_symbols(lib_path).and_then(|references| {
+                    with_stdout(|out| print_undefined(out, &references, color, demangle))
+                })
+            } else {
+                symbols_by_member(lib_path).and_then(|members| {
+                    with_stdout(|out| print_symbols(out, &members, color, demangle))
+                })
+            };
+            match printed {
+                Ok(_) => return ExitCode::SUCCESS,
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("dlls", cfg)) => {
+            let Some(lib_path) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
+            let dlls = lib_dlls(lib_path, import_kind(cfg));
+            match dlls.and_then(|dlls| with_stdout(|out| print_dlls(out, &dlls))) {
+                Ok(_) => return ExitCode::SUCCESS,
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("def", cfg)) => {
+            let Some(lib_path) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
+            let out = cfg.get_one::<OsString>("out");
+            let checked = match out {
+                Some(out) if !cfg.get_flag("force") => check_overwrite(lib_path, out),
+                _ => Ok(()),
+            };
+            let def = checked.and_then(|()| lib_to_def(lib_path, cfg.get_flag("undecorate")));
+            let result = def.and_then(|def| match out {
+                Some(out) => fs::write(out, def).map_err(|e| WinlibError::IoError {
+                    msg: format!("unable to write module-definition file to {}", out.display()),
+                    cause: e,
+                }),
+                None => write_stdout(&def),
+            });
+            match result {
+                Ok(_) => return ExitCode::SUCCESS,
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("fromdef", cfg)) => {
+            let Some(def_path) = cfg.get_one::<OsString>("DEF_PATH") else { unreachable!() };
+            let Some(out_lib) = cfg.get_one::<OsString>("out") else { unreachable!() };
+            let dll = cfg.get_one::<String>("dll");
+            let machine = machine_type(cfg.get_one::<String>("machine").map_or("x64", |s| s));
+            let force = cfg.get_flag("force");
+            match lib_from_def(def_path, out_lib, dll.map(String::as_str), machine, force) {
+                Ok(_) => return ExitCode::SUCCESS,
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("extract", cfg)) => {
+            let Some(lib_path) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
+            let Some(out_dir) = cfg.get_one::<PathBuf>("out") else { unreachable!() };
+            let pattern = patterns(cfg, "name").pop();
+            match extract_lib(lib_path, out_dir, pattern.as_ref()) {
+                Ok(_) => return ExitCode::SUCCESS,
+                Err(e) => return report_error(&e, json_errors),
+            }
+        }
+        Some(("split", cfg)) => {
+            let Some(lib_path) = cfg.get_one::<OsString>("LIB_PATH") else { unreachable!() };
+            let Some(out_dir) = cfg.get_one::<PathBuf>("out-dir") else { unreachable!() };
+            match split_lib(lib_path, out_dir, archive_format(cfg), cfg.get_flag("force")) {
+                Ok(paths) => {
+                    let text: String = paths
+                        .iter()
+                        .filter(|_| !quiet)
+                        .map(|path| format!("{}\n", path.display()))
+                        .collect();
+                    if let Err(e) = write_stdout(&text) {
+                        return report_error(&e, json_errors);
+                    }
+                    return ExitCode::SUCCESS;
                 }
+                Err(e) => return report_error(&e, json_errors),
             }
         }
         _ => (),
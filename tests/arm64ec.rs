@@ -0,0 +1,90 @@
+mod common;
+
+use common::{archive_headers, fixture, special_members, success, temp_dir, winlib};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Write a lib holding arm64ec.obj and the x64 object baz.obj.
+fn arm64ec_lib(dir: &Path) -> PathBuf {
+    let lib = dir.join("in.lib");
+    fs::write(&lib, b"!<arch>\n").unwrap();
+    success(
+        winlib()
+            .arg("add")
+            .arg(&lib)
+            .arg(fixture("arm64ec.obj"))
+            .arg(fixture("baz.obj"))
+            .arg("--mixed"),
+    );
+    lib
+}
+
+#[test]
+fn arch_is_arm64ec() {
+    let members =
+        success(winlib().arg("list").arg("--arch").arg(arm64ec_lib(&temp_dir("arm64ec-arch"))));
+    assert!(members.contains("  arm64ec   arm64ec.obj\n"), "{members}");
+    assert!(members.contains("  x86_64    baz.obj\n"), "{members}");
+}
+
+#[test]
+fn coff_libs_get_ec_symbols() {
+    let dir = temp_dir("arm64ec-ec-symbols");
+    let lib = arm64ec_lib(&dir);
+    let out = dir.join("out.lib");
+    success(
+        winlib().arg("create").arg("--from").arg(&lib).arg(&out).arg("--archive-kind").arg("coff"),
+    );
+    // The COFF format has a second symbol index after the first.
+    assert_eq!(special_members(&out), [2, 0, 1]);
+    // The symbols of ARM64EC and x64 objects go in the EC map.
+    let data = fs::read(&out).unwrap();
+    let (offset, _) = archive_headers(&data)[2];
+    let ec_symbols = &data[offset + 60..];
+    assert!(ec_symbols.starts_with(b"\x02\0\0\0"), "{ec_symbols:?}");
+    assert!(ec_symbols.windows(5).any(|name| name == b"ecfn\0"));
+
+    // Rewriting it keeps a single EC symbol map.
+    let again = dir.join("again.lib");
+    success(winlib().arg("create").arg("--from").arg(&out).arg(&again));
+    assert_eq!(special_members(&again), [2, 0, 1]);
+}
+
+#[test]
+fn gnu_libs_have_no_ec_symbols() {
+    let dir = temp_dir("arm64ec-gnu");
+    let out = dir.join("out.lib");
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(arm64ec_lib(&dir))
+            .arg(&out)
+            .arg("--archive-kind")
+            .arg("gnu"),
+    );
+    assert_eq!(special_members(&out), [1, 0, 0]);
+}
+
+#[test]
+fn split_skips_the_old_ec_symbols() {
+    let dir = temp_dir("arm64ec-split");
+    let lib = dir.join("coff.lib");
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(arm64ec_lib(&dir))
+            .arg(&lib)
+            .arg("--archive-kind")
+            .arg("coff"),
+    );
+    let members = success(winlib().arg("list").arg(&lib));
+    assert!(members.contains("2 members,") && !members.contains("ECSYMBOLS"), "{members}");
+
+    success(winlib().arg("split").arg(&lib).arg("--out-dir").arg(dir.join("split")));
+    let misc = dir.join("split/misc.lib");
+    assert_eq!(special_members(&misc), [2, 0, 1]);
+    let members = success(winlib().arg("list").arg(&misc));
+    assert!(members.contains("2 members,") && !members.contains("ECSYMBOLS"), "{members}");
+}
@@ -0,0 +1,92 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The path of a file in `tests/fixtures`.
+pub fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+/// An empty directory for the outputs of the test `name`.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("winlib-test-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A command that runs the `winlib` binary.
+pub fn winlib() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_winlib"))
+}
+
+/// Run `command`, check that it succeeded and return its stdout.
+pub fn success(command: &mut Command) -> String {
+    let output = command.output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{command:?} failed: {stderr}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Run `command`, check that it failed and return its stderr.
+pub fn failure(command: &mut Command) -> String {
+    let output = command.output().unwrap();
+    assert!(!output.status.success(), "{command:?} succeeded");
+    String::from_utf8(output.stderr).unwrap()
+}
+
+/// Format a 60 byte archive member header. Empty fields are left blank.
+pub fn archive_header(
+    name: &str,
+    date: &str,
+    uid: &str,
+    gid: &str,
+    mode: &str,
+    size: u64,
+) -> String {
+    format!("{name:<16}{date:<12}{uid:<6}{gid:<6}{mode:<8}{size:<10}`\n")
+}
+
+/// Get the offset and header of every member of an archive, including the
+/// symbol index and long name table.
+pub fn archive_headers(lib: &[u8]) -> Vec<(usize, &[u8])> {
+    assert!(lib.starts_with(b"!<arch>\n"));
+    let mut headers = Vec::new();
+    let mut offset = 8;
+    while offset < lib.len() {
+        let header = &lib[offset..offset + 60];
+        let size: usize = std::str::from_utf8(&header[48..58]).unwrap().trim().parse().unwrap();
+        headers.push((offset, header));
+        offset += 60 + size + size % 2;
+    }
+    headers
+}
+
+/// Get the header of each member of an archive other than the symbol index
+/// and long name table.
+pub fn member_headers(lib: &[u8]) -> Vec<&[u8]> {
+    archive_headers(lib)
+        .into_iter()
+        .map(|(_, header)| header)
+        .filter(|header| {
+            let name = std::str::from_utf8(&header[..16]).unwrap().trim_end();
+            !matches!(name, "/" | "//" | "/SYM64/" | "/<ECSYMBOLS>/")
+        })
+        .collect()
+}
+
+/// The number of symbol index, long name table and EC symbol map members in
+/// the lib at `path`.
+///
+/// COFF archives have two symbol index members and GNU archives have one.
+pub fn special_members(path: &Path) -> [usize; 3] {
+    let data = fs::read(path).unwrap();
+    let headers = archive_headers(&data);
+    ["/", "//", "/<ECSYMBOLS>/"].map(|name| {
+        let is_named = |header: &[u8]| header[..16].trim_ascii_end() == name.as_bytes();
+        headers.iter().filter(|(_, header)| is_named(header)).count()
+    })
+}
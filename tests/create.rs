@@ -0,0 +1,313 @@
+mod common;
+
+use common::{archive_header, failure, fixture, member_headers, success, temp_dir, winlib};
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+
+#[test]
+fn overwrite_the_old_lib() {
+    let dir = temp_dir("create-overwrite-old-lib");
+    let lib = dir.join("objects.lib");
+    let excluded = dir.join("excluded.lib");
+    fs::copy(fixture("objects.lib"), &lib).unwrap();
+
+    let stderr = failure(winlib().arg("create").arg("--from").arg(&lib).arg(&lib));
+    assert!(stderr.contains("--force"), "{stderr}");
+
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(&lib)
+            .arg(&lib)
+            .arg("--force")
+            .arg("--exclude-symbol")
+            .arg("baz")
+            .arg("--save-excluded")
+            .arg(&excluded),
+    );
+    let members = success(winlib().arg("list").arg(&lib));
+    assert!(members.contains("foo.obj"), "{members}");
+    assert!(!members.contains("an_object_with_a_long_name.obj"), "{members}");
+    let members = success(winlib().arg("list").arg(&excluded));
+    assert!(members.contains("an_object_with_a_long_name.obj"), "{members}");
+    assert!(members.contains("1 member,"), "{members}");
+}
+
+#[test]
+fn rename_dll() {
+    let dir = temp_dir("create-rename-dll");
+    let lib = dir.join("kernelbase.lib");
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(fixture("kernel32.lib"))
+            .arg(&lib)
+            .arg("--rename-dll")
+            .arg("KERNEL32.DLL=kernelbase.dll"),
+    );
+
+    let imports = success(winlib().arg("symbols").arg("--imports").arg(&lib));
+    assert!(imports.contains("import descriptor for kernelbase.dll"), "{imports}");
+    assert!(!imports.contains("import descriptor for kernel32.dll"), "{imports}");
+    let members = success(winlib().arg("list").arg("--symbols").arg(&lib));
+    assert!(members.contains("GetLastError (kernelbase.dll)\n"), "{members}");
+    assert!(!members.contains("(kernel32.dll)"), "{members}");
+    assert!(members.contains("__IMPORT_DESCRIPTOR_kernelbase\n"), "{members}");
+    assert!(members.contains("kernelbase_NULL_THUNK_DATA\n"), "{members}");
+    assert!(!members.contains("kernel32_NULL_THUNK_DATA"), "{members}");
+}
+
+#[test]
+fn rename_dll_named_by_a_gnu_object() {
+    let dir = temp_dir("create-rename-gnu-dll");
+    let stderr = failure(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(fixture("gnu-iname.lib"))
+            .arg(dir.join("out.lib"))
+            .arg("--rename-dll")
+            .arg("kernel32.dll=kernelbase.dll"),
+    );
+    assert!(stderr.contains("only MSVC-style import objects"), "{stderr}");
+    assert!(!dir.join("out.lib").exists());
+}
+
+#[test]
+fn exclude_file_from_list_output() {
+    let dir = temp_dir("create-exclude-file");
+    let offsets = dir.join("offsets.txt");
+    let lib = dir.join("out.lib");
+    let listed = success(
+        winlib()
+            .arg("list")
+            .arg(fixture("objects.lib"))
+            .arg("--filter")
+            .arg("weak*")
+            .arg("--symbols")
+            .arg("--sections"),
+    );
+    fs::write(&offsets, listed).unwrap();
+
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(fixture("objects.lib"))
+            .arg(&lib)
+            .arg("--exclude-file")
+            .arg(&offsets)
+            .arg("--strict"),
+    );
+    let members = success(winlib().arg("list").arg(&lib));
+    assert!(members.contains("foo.obj"), "{members}");
+    assert!(members.contains("an_object_with_a_long_name.obj"), "{members}");
+    assert!(!members.contains("weak.obj"), "{members}");
+}
+
+#[test]
+fn members_from_list_output() {
+    let dir = temp_dir("create-members-from");
+    let list = dir.join("members.txt");
+    let lib = dir.join("out.lib");
+    let listed = success(winlib().arg("list").arg(fixture("objects.lib")).arg("--symbols"));
+    // Keep every member but the first, in reverse order.
+    let mut lines: Vec<&str> = listed.lines().collect();
+    let rows: Vec<usize> = (0..lines.len()).filter(|&i| lines[i].starts_with("     0x")).collect();
+    assert_eq!(rows.len(), 3, "{listed}");
+    lines.drain(rows[0]..rows[1]);
+    let (header, rest) = lines.split_at(1);
+    let mut reordered = header.to_vec();
+    reordered.extend(rest.iter().rev());
+    fs::write(&list, reordered.join("\n")).unwrap();
+
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(fixture("objects.lib"))
+            .arg(&lib)
+            .arg("--members-from")
+            .arg(&list),
+    );
+    let members = success(winlib().arg("list").arg(&lib));
+    let weak = members.find("weak.obj").expect(&members);
+    let long = members.find("an_object_with_a_long_name.obj").expect(&members);
+    assert!(weak < long, "{members}");
+    assert!(!members.contains("foo.obj"), "{members}");
+}
+
+/// Write a GNU archive with a sparse 4 GiB member followed by baz.obj, whose
+/// data starts at 0x1_0000_0080.
+fn write_lib_over_4_gib(path: &Path) {
+    let header = |name, size| archive_header(name, "0", "0", "0", "644", size);
+    let object = fs::read(fixture("baz.obj")).unwrap();
+    let mut lib = fs::File::create(path).unwrap();
+    lib.write_all(b"!<arch>\n").unwrap();
+    lib.write_all(header("big.bin/", 1 << 32).as_bytes()).unwrap();
+    lib.seek(SeekFrom::Current(1 << 32)).unwrap();
+    lib.write_all(header("baz.obj/", object.len() as u64).as_bytes()).unwrap();
+    lib.write_all(&object).unwrap();
+}
+
+#[test]
+fn exclude_a_member_beyond_4_gib() {
+    let dir = temp_dir("create-beyond-4-gib");
+    let lib = dir.join("big.lib");
+    write_lib_over_4_gib(&lib);
+
+    let members = success(winlib().arg("list").arg(&lib));
+    assert!(members.contains("0x100000080       0x10F  baz.obj\n"), "{members}");
+
+    let create = || {
+        let mut command = winlib();
+        command.arg("create").arg("--from").arg(&lib).arg(dir.join("out.lib")).arg("--dry-run");
+        command.arg("--strict");
+        command
+    };
+    let selection = success(create().arg("--exclude").arg("0x100000080"));
+    assert!(selection.ends_with("EXCLUDE:\n0x100000080  baz.obj\n"), "{selection}");
+    // The offset truncated to 32 bits mustn't match.
+    let stderr = failure(create().arg("--exclude").arg("0x80"));
+    assert!(stderr.contains("no members at the excluded offsets 0x80"), "{stderr}");
+    fs::remove_file(&lib).unwrap();
+}
+
+#[test]
+fn save_excluded_lib_has_its_own_index() {
+    let dir = temp_dir("create-save-excluded-index");
+    let both = dir.join("both.lib");
+    let kernel32 = dir.join("kernel32.lib");
+    let user32 = dir.join("user32.lib");
+    success(
+        winlib()
+            .arg("merge")
+            .arg(fixture("kernel32.lib"))
+            .arg(fixture("user32.lib"))
+            .arg("--out")
+            .arg(&both),
+    );
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(&both)
+            .arg(&kernel32)
+            .arg("--exclude-dll")
+            .arg("user32.dll")
+            .arg("--save-excluded")
+            .arg(&user32),
+    );
+
+    let index = success(winlib().arg("symbols").arg("--index").arg(&user32));
+    let mut symbols: Vec<&str> =
+        index.lines().map(|line| line.rsplit(' ').next().unwrap()).collect();
+    symbols.sort();
+    assert_eq!(symbols, ["GetDC", "MessageBoxW", "__imp_GetDC", "__imp_MessageBoxW"], "{index}");
+    let members = success(winlib().arg("list").arg("--dll").arg(&user32));
+    assert!(!members.contains("kernel32.dll"), "{members}");
+
+    let index = success(winlib().arg("symbols").arg("--index").arg(&kernel32));
+    assert!(index.contains(" kernel32.dll: __imp_GetLastError\n"), "{index}");
+    assert!(!index.contains("MessageBoxW") && !index.contains("GetDC"), "{index}");
+    for lib in [&kernel32, &user32] {
+        success(winlib().arg("verify").arg("--index").arg(lib));
+    }
+}
+
+#[test]
+fn save_excluded_and_merge_back() {
+    let dir = temp_dir("create-save-excluded-merge");
+    let included = dir.join("included.lib");
+    let excluded = dir.join("excluded.lib");
+    let merged = dir.join("merged.lib");
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(fixture("objects.lib"))
+            .arg(&included)
+            .arg("--exclude-symbol")
+            .arg("baz")
+            .arg("--save-excluded")
+            .arg(&excluded)
+            .arg("--sort"),
+    );
+    success(winlib().arg("merge").arg(&included).arg(&excluded).arg("--out").arg(&merged));
+
+    success(winlib().arg("diff").arg(fixture("objects.lib")).arg(&merged));
+    let symbols = |lib: &Path| {
+        let symbols = success(winlib().arg("symbols").arg(lib));
+        let mut lines: Vec<String> = symbols.lines().map(String::from).collect();
+        lines.sort();
+        lines
+    };
+    assert_eq!(symbols(&fixture("objects.lib")), symbols(&merged));
+}
+
+#[test]
+fn exclude_an_offset_from_list() {
+    let dir = temp_dir("create-exclude-listed-offset");
+    let lib = dir.join("out.lib");
+    let members = success(winlib().arg("list").arg(fixture("objects.lib")));
+    let line = members
+        .lines()
+        .find(|line| line.ends_with("  an_object_with_a_long_name.obj"))
+        .expect(&members);
+    // Pass the offset with the same case and padding as `list` prints it.
+    let offset = &line[..10];
+    assert!(offset.trim_start().starts_with("0x") && offset.contains(char::is_uppercase));
+
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(fixture("objects.lib"))
+            .arg(&lib)
+            .arg("--exclude")
+            .arg(offset)
+            .arg("--strict"),
+    );
+    let members = success(winlib().arg("list").arg(&lib));
+    assert!(!members.contains("an_object_with_a_long_name.obj"), "{members}");
+    assert!(members.contains("2 members,"), "{members}");
+}
+
+#[test]
+fn preserve_metadata_keeps_member_headers() {
+    let dir = temp_dir("create-preserve-metadata");
+    let lib = dir.join("in.lib");
+    let out = dir.join("out.lib");
+    let mut data = b"!<arch>\n".to_vec();
+    for (name, date, id, mode) in
+        [("foo.obj/", "1700000000", "1000", "100644"), ("baz.obj/", "", "", "")]
+    {
+        let object = fs::read(fixture(&name.replace('/', ""))).unwrap();
+        data.extend(archive_header(name, date, id, id, mode, object.len() as u64).as_bytes());
+        data.extend(&object);
+        if object.len() % 2 == 1 {
+            data.push(b'\n');
+        }
+    }
+    fs::write(&lib, &data).unwrap();
+
+    success(winlib().arg("create").arg("--from").arg(&lib).arg(&out).arg("--preserve-metadata"));
+    let new = fs::read(&out).unwrap();
+    assert_eq!(member_headers(&new), member_headers(&data));
+}
+
+#[test]
+fn keep_an_existing_tmp_file() {
+    let dir = temp_dir("create-existing-tmp");
+    let lib = dir.join("new.lib");
+    let tmp = dir.join("new.lib.tmp");
+    fs::write(&tmp, "user data").unwrap();
+    success(winlib().arg("create").arg("--from").arg(fixture("objects.lib")).arg(&lib));
+    assert_eq!(fs::read(&tmp).unwrap(), b"user data");
+    // Only the new lib and the user's file are left behind.
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    assert!(success(winlib().arg("list").arg(&lib)).contains("foo.obj"));
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::{failure, fixture, success, temp_dir, winlib};
+use std::fs;
+
+#[test]
+fn overwrite_the_lib() {
+    let dir = temp_dir("fromdef-overwrite");
+    let def = dir.join("misc.def");
+    fs::write(&def, "LIBRARY misc.dll\nEXPORTS\n  foo\n").unwrap();
+    let lib = dir.join("misc.lib");
+    fs::write(&lib, "not a lib").unwrap();
+
+    let stderr = failure(winlib().arg("fromdef").arg(&def).arg("--out").arg(&lib));
+    assert!(stderr.contains("refusing to overwrite"), "{stderr}");
+    assert_eq!(fs::read(&lib).unwrap(), b"not a lib");
+    success(winlib().arg("fromdef").arg(&def).arg("--out").arg(&lib).arg("--force"));
+    assert!(success(winlib().arg("symbols").arg(&lib)).contains("misc.dll: foo\n"));
+}
+
+#[test]
+fn overwrite_the_def() {
+    let dir = temp_dir("def-overwrite");
+    let def = dir.join("kernel32.def");
+    fs::write(&def, "old").unwrap();
+
+    let mut write_def = winlib();
+    write_def.arg("def").arg(fixture("kernel32.lib")).arg("--out").arg(&def);
+    let stderr = failure(&mut write_def);
+    assert!(stderr.contains("refusing to overwrite"), "{stderr}");
+    assert_eq!(fs::read(&def).unwrap(), b"old");
+    success(write_def.arg("--force"));
+    assert!(fs::read_to_string(&def).unwrap().contains("GetLastError"));
+}
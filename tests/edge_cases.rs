@@ -0,0 +1,46 @@
+mod common;
+
+use common::{failure, fixture, success, temp_dir, winlib};
+use std::fs;
+
+#[test]
+fn zero_byte_file() {
+    let lib = temp_dir("edge-zero-byte").join("zero.lib");
+    fs::write(&lib, b"").unwrap();
+    let stderr = failure(winlib().arg("list").arg(&lib));
+    assert!(stderr.contains("zero.lib is empty, it isn't a lib or archive file"), "{stderr}");
+    assert!(!stderr.contains("not a recognised archive"), "{stderr}");
+}
+
+#[test]
+fn empty_archive() {
+    let lib = temp_dir("edge-empty-archive").join("empty.lib");
+    fs::write(&lib, b"!<arch>\n").unwrap();
+    assert_eq!(success(winlib().arg("list").arg(&lib)), "(no members)\n");
+}
+
+#[test]
+fn truncated_archive() {
+    let lib = temp_dir("edge-truncated").join("truncated.lib");
+    let data = fs::read(fixture("objects.lib")).unwrap();
+    // Cut the archive off in the middle of foo.obj's data.
+    fs::write(&lib, &data[..0x108 + 0x40]).unwrap();
+    let stderr = failure(winlib().arg("list").arg("--type").arg(&lib));
+    assert!(stderr.contains("archive member at 0x108 in "), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+    let out = lib.with_extension("out");
+    failure(winlib().arg("create").arg("--from").arg(&lib).arg(&out));
+    assert!(!out.exists());
+}
+
+#[test]
+fn list_a_truncated_lib() {
+    let lib = temp_dir("edge-list-truncated").join("truncated.lib");
+    let data = fs::read(fixture("objects.lib")).unwrap();
+    fs::write(&lib, &data[..0x108 + 0x40]).unwrap();
+    // Only the headers are needed to list the members.
+    let members = success(winlib().arg("list").arg(&lib));
+    assert!(members.contains("     0x108       0x19F  foo.obj\n"), "{members}");
+    let members = success(winlib().arg("list").arg("--verbose").arg(&lib));
+    assert!(members.contains("foo.obj"), "{members}");
+}
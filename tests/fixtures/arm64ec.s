@@ -0,0 +1,4 @@
+.text
+.globl ecfn
+ecfn:
+  ret
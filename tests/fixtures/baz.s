@@ -0,0 +1,3 @@
+.text
+.globl baz
+baz: ret
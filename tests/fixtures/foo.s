@@ -0,0 +1,8 @@
+.text
+.globl foo
+foo: ret
+.globl bar
+bar: call ext_fn
+ret
+.section .drectve,"yn"
+.ascii " /DEFAULTLIB:foo"
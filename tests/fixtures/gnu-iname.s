@@ -0,0 +1,5 @@
+# The object GNU dlltool writes to hold the DLL name of an import lib.
+.section .idata$7,"dr"
+.globl _libkernel32_a_iname
+_libkernel32_a_iname:
+.asciz "kernel32.dll"
@@ -0,0 +1,3 @@
+# A weak external with a default that some other object has to define.
+.weak wfn
+.set wfn, default_wfn
@@ -0,0 +1,6 @@
+.text
+.globl bar
+bar:
+  ret
+.weak foo
+.set foo, bar
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{fixture, success, winlib};
+use winlib::{DllImports, SymbolChange, diff_libs, lib_dlls, verify_symbol_index};
+
+#[test]
+fn diff_returns_the_changes() {
+    let old = fixture("kernel32.lib");
+    let new = fixture("kernel32-extra.lib");
+    let changes = diff_libs(old.as_os_str(), new.as_os_str(), false).unwrap();
+    assert!(changes.contains(&SymbolChange::Removed("GetLastError".into())));
+    assert!(changes.contains(&SymbolChange::Added("CloseHandle".into())));
+    // Added symbols come after the removed ones.
+    assert!(matches!(changes.last(), Some(SymbolChange::Added(_))));
+}
+
+#[test]
+fn dlls_are_counted() {
+    let dlls = lib_dlls(fixture("kernel32.lib").as_os_str(), None).unwrap();
+    let kernel32 = DllImports { name: "kernel32.dll".into(), symbols: 4, ordinals: 1 };
+    assert_eq!(dlls, [kernel32]);
+}
+
+#[test]
+fn valid_index_has_no_mismatches() {
+    let mismatches = verify_symbol_index(fixture("kernel32.lib").as_os_str()).unwrap();
+    assert_eq!(mismatches, []);
+}
+
+#[test]
+fn stats_go_to_stderr() {
+    let list = || {
+        let mut list = winlib();
+        list.arg("list").arg("--format").arg("json").arg(fixture("kernel32.lib"));
+        list
+    };
+    let output = list().arg("--stats").output().unwrap();
+    assert!(output.status.success());
+    // The JSON on stdout is unchanged so it can still be piped to another tool.
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), success(&mut list()));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("7 members, 0x"), "{stderr}");
+    assert!(stderr.contains(" bytes read in "), "{stderr}");
+}
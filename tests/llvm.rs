@@ -0,0 +1,46 @@
+//! Import libs written by `llvm-dlltool`.
+
+mod common;
+
+use common::{fixture, success, temp_dir, winlib};
+
+#[test]
+fn classify_members() {
+    let members = success(winlib().arg("list").arg("--type").arg(fixture("kernel32.lib")));
+    let kinds: Vec<&str> = members
+        .lines()
+        .filter_map(|line| line.strip_suffix("  kernel32.dll"))
+        .map(|line| line.split_whitespace().nth(2).unwrap())
+        .collect();
+    // The import descriptor, null import descriptor and null thunk, then the short imports.
+    let objects = ["import-object"; 3];
+    assert_eq!(kinds, [&objects[..], &["import"; 4]].concat(), "{members}");
+    assert!(success(winlib().arg("verify").arg(fixture("kernel32.lib"))).contains("7 members"));
+}
+
+#[test]
+fn short_imports() {
+    let symbols = success(winlib().arg("list").arg("--symbols").arg(fixture("kernel32.lib")));
+    assert!(symbols.contains(" GetLastError (kernel32.dll)\n"), "{symbols}");
+    assert!(symbols.contains(" ordfunc @5 NONAME (kernel32.dll)\n"), "{symbols}");
+    let data =
+        success(winlib().arg("list").arg("--import-type").arg("data").arg(fixture("kernel32.lib")));
+    assert!(data.contains("1 member,"), "{data}");
+
+    let dlls = success(winlib().arg("dlls").arg(fixture("kernel32.lib")));
+    assert_eq!(dlls, "kernel32.dll: 4 symbols (1 by ordinal)\n");
+}
+
+#[test]
+fn exclude_idata() {
+    let lib = temp_dir("llvm-exclude-idata").join("out.lib");
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(fixture("kernel32.lib"))
+            .arg(&lib)
+            .arg("--exclude-idata"),
+    );
+    assert!(success(winlib().arg("list").arg(&lib)).contains("(no members)"));
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use common::{fixture, success, temp_dir, winlib};
+use std::fs;
+
+const LONG_NAME: &str = "an_object_with_a_long_name.obj";
+
+#[test]
+fn list_resolves_long_names() {
+    let members = success(winlib().arg("list").arg(fixture("objects.lib")));
+    assert!(members.contains(&format!("  {LONG_NAME}\n")), "{members}");
+    assert!(!members.contains("  /0\n"), "{members}");
+}
+
+#[test]
+fn extract_uses_the_long_name() {
+    let dir = temp_dir("long-names-extract");
+    success(winlib().arg("extract").arg(fixture("objects.lib")).arg("--out").arg(&dir));
+    let extracted = fs::read(dir.join(LONG_NAME)).unwrap();
+    assert_eq!(extracted, fs::read(fixture("baz.obj")).unwrap());
+}
+
+#[test]
+fn create_keeps_long_names_in_the_name_table() {
+    let lib = temp_dir("long-names-create").join("out.lib");
+    success(winlib().arg("create").arg("--from").arg(fixture("objects.lib")).arg(&lib));
+    let table = success(winlib().arg("list").arg("--name-table").arg(&lib));
+    // Once in the table itself and once as the member's name reference.
+    let references = table.lines().filter(|line| line.ends_with(&format!(" /0  {LONG_NAME}")));
+    assert_eq!(references.count(), 2, "{table}");
+    assert!(table.contains("inline  foo.obj\n"), "{table}");
+}
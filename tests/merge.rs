@@ -0,0 +1,71 @@
+mod common;
+
+use common::{failure, fixture, special_members, success, temp_dir, winlib};
+
+#[test]
+fn import_libs_for_the_same_dll() {
+    let dir = temp_dir("merge-same-dll");
+    let out = dir.join("kernel32.lib");
+    success(
+        winlib()
+            .arg("merge")
+            .arg(fixture("kernel32.lib"))
+            .arg(fixture("kernel32-extra.lib"))
+            .arg("--out")
+            .arg(&out),
+    );
+    let symbols = success(winlib().arg("symbols").arg(&out));
+    assert!(symbols.contains("kernel32.dll: GetLastError\n"), "{symbols}");
+    assert!(symbols.contains("kernel32.dll: ExitProcess\n"), "{symbols}");
+}
+
+#[test]
+fn duplicate_objects() {
+    let dir = temp_dir("merge-duplicate-objects");
+    let stderr = failure(
+        winlib()
+            .arg("merge")
+            .arg(fixture("objects.lib"))
+            .arg(fixture("objects.lib"))
+            .arg("--out")
+            .arg(dir.join("out.lib")),
+    );
+    assert!(stderr.contains("duplicate member foo.obj"), "{stderr}");
+}
+
+#[test]
+fn keep_the_format_of_the_first_lib() {
+    let dir = temp_dir("merge-gnu");
+    let merge = |out: &std::path::Path| {
+        let mut merge = winlib();
+        merge.arg("merge").arg(fixture("kernel32.lib")).arg(fixture("objects.lib")).arg("--out");
+        merge.arg(out);
+        merge
+    };
+    // The first lib came from llvm-dlltool so it's a GNU archive with one symbol index.
+    let gnu = dir.join("gnu.lib");
+    success(&mut merge(&gnu));
+    assert_eq!(special_members(&gnu)[0], 1);
+    let coff = dir.join("coff.lib");
+    success(merge(&coff).arg("--archive-kind").arg("coff"));
+    assert_eq!(special_members(&coff)[0], 2);
+}
+
+#[test]
+fn overwrite_an_input() {
+    let dir = temp_dir("merge-overwrite-input");
+    let lib = dir.join("kernel32.lib");
+    std::fs::copy(fixture("kernel32.lib"), &lib).unwrap();
+    let merge = |lib: &std::path::Path| {
+        let mut merge = winlib();
+        merge.arg("merge").arg(lib).arg(fixture("objects.lib")).arg("--out").arg(lib);
+        merge
+    };
+    let stderr = failure(&mut merge(&lib));
+    assert!(stderr.contains("refusing to overwrite the input lib"), "{stderr}");
+    assert_eq!(std::fs::read(&lib).unwrap(), std::fs::read(fixture("kernel32.lib")).unwrap());
+
+    success(merge(&lib).arg("--force"));
+    let members = success(winlib().arg("list").arg(&lib));
+    assert!(members.contains("foo.obj"), "{members}");
+}
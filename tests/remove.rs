@@ -0,0 +1,44 @@
+mod common;
+
+use common::{failure, fixture, success, temp_dir, winlib};
+use std::fs;
+
+#[test]
+fn remove_in_place() {
+    let dir = temp_dir("remove-in-place");
+    let lib = dir.join("objects.lib");
+    fs::copy(fixture("objects.lib"), &lib).unwrap();
+
+    let remove = || {
+        let mut command = winlib();
+        command.arg("remove").arg(&lib).arg("--out").arg(&lib).arg("--name").arg("weak*");
+        command
+    };
+    let stderr = failure(&mut remove());
+    assert!(stderr.contains("--force"), "{stderr}");
+    success(remove().arg("--force"));
+
+    let members = success(winlib().arg("list").arg(&lib));
+    assert!(members.contains("foo.obj"), "{members}");
+    assert!(members.contains("an_object_with_a_long_name.obj"), "{members}");
+    assert!(!members.contains("weak.obj"), "{members}");
+    // Nothing is left behind by the temporary file used for the rename.
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+}
+
+#[test]
+fn remove_every_member() {
+    let out = temp_dir("remove-every-member").join("empty.lib");
+    let output = winlib()
+        .arg("remove")
+        .arg(fixture("objects.lib"))
+        .arg("--out")
+        .arg(&out)
+        .arg("--name")
+        .arg("*")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("help: 3 members matched an excluded name pattern"), "{stderr}");
+}
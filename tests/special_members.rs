@@ -0,0 +1,24 @@
+//! The symbol index and long name table are written fresh rather than copied.
+
+mod common;
+
+use common::{fixture, special_members, success, temp_dir, winlib};
+
+#[test]
+fn rewrite_has_one_index_and_name_table() {
+    let dir = temp_dir("special-members-rewrite");
+    for (kind, index) in [("gnu", 1), ("coff", 2)] {
+        let lib = dir.join(format!("{kind}.lib"));
+        let again = dir.join(format!("{kind}-again.lib"));
+        let mut create = winlib();
+        create.arg("create").arg("--from").arg(fixture("objects.lib")).arg(&lib);
+        success(create.arg("--archive-kind").arg(kind));
+        success(winlib().arg("create").arg("--from").arg(&lib).arg(&again));
+        for lib in [&lib, &again] {
+            assert_eq!(special_members(lib), [index, 1, 0], "{}", lib.display());
+            success(winlib().arg("verify").arg("--index").arg(lib));
+            let members = success(winlib().arg("list").arg(lib));
+            assert!(members.contains("3 members,"), "{members}");
+        }
+    }
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::{failure, fixture, special_members, success, temp_dir, winlib};
+
+#[test]
+fn keep_the_format_of_the_lib() {
+    let dir = temp_dir("split-gnu");
+    success(winlib().arg("split").arg(fixture("kernel32.lib")).arg("--out-dir").arg(&dir));
+    // The fixture came from llvm-dlltool so it's a GNU archive with one symbol index.
+    assert_eq!(special_members(&dir.join("kernel32.lib"))[0], 1);
+
+    let coff = dir.join("coff");
+    success(
+        winlib()
+            .arg("split")
+            .arg(fixture("kernel32.lib"))
+            .arg("--out-dir")
+            .arg(&coff)
+            .arg("--archive-kind")
+            .arg("coff"),
+    );
+    assert_eq!(special_members(&coff.join("kernel32.lib"))[0], 2);
+}
+
+#[test]
+fn misc_dll_keeps_its_own_lib() {
+    let dir = temp_dir("split-misc-dll");
+    let def = dir.join("misc.def");
+    std::fs::write(&def, "LIBRARY misc.dll\nEXPORTS\n  foo\n").unwrap();
+    let lib = dir.join("in.lib");
+    success(winlib().arg("fromdef").arg(&def).arg("--out").arg(&lib));
+    // An object named after the DLL isn't one of its imports.
+    success(
+        winlib()
+            .arg("add")
+            .arg(&lib)
+            .arg(fixture("baz.obj"))
+            .arg("--as")
+            .arg("misc.dll")
+            .arg("--mixed")
+            .arg("--allow-duplicates"),
+    );
+
+    let out = dir.join("out");
+    let output = winlib().arg("split").arg(&lib).arg("--out-dir").arg(&out).output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("written to misc-1.lib because misc.lib is used"), "{stderr}");
+
+    let symbols = success(winlib().arg("symbols").arg(out.join("misc.lib")));
+    assert!(symbols.contains("misc.dll: foo\n"), "{symbols}");
+    assert!(symbols.contains("misc.dll: __NULL_IMPORT_DESCRIPTOR\n"), "{symbols}");
+    assert!(!symbols.contains("baz"), "{symbols}");
+    let misc = success(winlib().arg("symbols").arg(out.join("misc-1.lib")));
+    assert_eq!(misc, "misc.dll: baz\n");
+}
+
+#[test]
+fn overwrite_existing_libs() {
+    let dir = temp_dir("split-overwrite");
+    let old = dir.join("kernel32.lib");
+    std::fs::write(&old, "not a lib").unwrap();
+    let mut split = winlib();
+    split.arg("split").arg(fixture("kernel32.lib")).arg("--out-dir").arg(&dir);
+    let stderr = failure(&mut split);
+    assert!(stderr.contains("--force"), "{stderr}");
+    assert_eq!(std::fs::read(&old).unwrap(), b"not a lib");
+
+    success(split.arg("--force"));
+    assert!(success(winlib().arg("list").arg(&old)).contains("7 members"));
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::{failure, fixture, success, temp_dir, winlib};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Write a lib holding `object` as foo.obj.
+fn lib_with(dir: &Path, object: &[u8]) -> PathBuf {
+    let object_path = dir.join("foo.obj");
+    let lib = dir.join("in.lib");
+    fs::write(&object_path, object).unwrap();
+    fs::write(&lib, b"!<arch>\n").unwrap();
+    success(winlib().arg("add").arg(&lib).arg(&object_path));
+    lib
+}
+
+#[test]
+fn strip_drectve() {
+    let dir = temp_dir("strip-drectve");
+    let out = dir.join("out.lib");
+    let lib = lib_with(&dir, &fs::read(fixture("foo.obj")).unwrap());
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(&lib)
+            .arg(&out)
+            .arg("--strip-section")
+            .arg(".drectve"),
+    );
+    let members = success(winlib().arg("list").arg("--symbols").arg("--sections").arg(&out));
+    assert!(members.contains(" .text ") && !members.contains(".drectve"), "{members}");
+    assert!(members.contains(" foo\n") && members.contains(" bar\n"), "{members}");
+}
+
+#[test]
+fn section_data_out_of_bounds() {
+    let dir = temp_dir("strip-out-of-bounds");
+    let mut object = fs::read(fixture("foo.obj")).unwrap();
+    // Point the data of .text, the first section, past the end of the object.
+    object[20 + 20..20 + 24].copy_from_slice(&0xfff0u32.to_le_bytes());
+    let lib = lib_with(&dir, &object);
+    let stderr = failure(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(&lib)
+            .arg(dir.join("out.lib"))
+            .arg("--strip-section")
+            .arg(".drectve"),
+    );
+    assert!(stderr.contains("the data of a section is truncated"), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
@@ -0,0 +1,61 @@
+//! Timings that are too slow and noisy to check by default. Run them with
+//! `cargo test --release --test timing -- --ignored --nocapture`.
+
+mod common;
+
+use common::{archive_header, fixture, member_headers, temp_dir, winlib};
+use std::fs;
+use std::path::Path;
+
+/// The time `create --exclude-idata --jobs <jobs>` spends parsing members, as reported by `--stats`.
+fn parse_time(lib: &Path, jobs: usize) -> f64 {
+    let output = winlib()
+        .arg("create")
+        .arg("--from")
+        .arg(lib)
+        .arg(lib.with_extension("out"))
+        .arg("--force")
+        .arg("--exclude-idata")
+        .arg("--jobs")
+        .arg(jobs.to_string())
+        .arg("--stats")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let time = stderr.lines().find_map(|line| line.strip_suffix("s parsing COFF members"));
+    time.unwrap_or_else(|| panic!("{stderr}")).parse().unwrap()
+}
+
+/// Compare parsing a large import lib on one thread with parsing it on several.
+#[test]
+#[ignore]
+fn exclude_idata_jobs() {
+    // The import objects and short imports written by llvm-dlltool, repeated to
+    // 35,000 members, about the size of an umbrella lib such as `onecore.lib`.
+    let fixture = fs::read(fixture("kernel32.lib")).unwrap();
+    let mut data = b"!<arch>\n".to_vec();
+    for copy in 0..5_000 {
+        for (index, header) in member_headers(&fixture).into_iter().enumerate() {
+            let offset = header.as_ptr() as usize - fixture.as_ptr() as usize + 60;
+            let size: usize = std::str::from_utf8(&header[48..58]).unwrap().trim().parse().unwrap();
+            let name = format!("m{copy}_{index}.o/");
+            data.extend(archive_header(&name, "0", "0", "0", "644", size as u64).as_bytes());
+            data.extend(&fixture[offset..offset + size]);
+            if size % 2 == 1 {
+                data.push(b'\n');
+            }
+        }
+    }
+    let lib = temp_dir("timing-jobs").join("big.lib");
+    fs::write(&lib, data).unwrap();
+
+    let cpus = std::thread::available_parallelism().map_or(1, |cpus| cpus.get());
+    let mut jobs = vec![1, 2, 4, cpus];
+    jobs.sort();
+    jobs.dedup();
+    for jobs in jobs {
+        let best = (0..5).map(|_| parse_time(&lib, jobs)).fold(f64::INFINITY, f64::min);
+        eprintln!("--jobs {jobs}: {best:.3}s parsing");
+    }
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::{archive_headers, fixture, success, temp_dir, winlib};
+use std::fs;
+
+#[test]
+fn only_dates_change() {
+    for input in ["objects.lib", "kernel32.lib"] {
+        let out = temp_dir("touch-only-dates").join(input);
+        success(
+            winlib()
+                .arg("touch")
+                .arg(fixture(input))
+                .arg("--out")
+                .arg(&out)
+                .arg("--mtime")
+                .arg("1700000000"),
+        );
+        let old = fs::read(fixture(input)).unwrap();
+        let new = fs::read(&out).unwrap();
+        assert_eq!(old.len(), new.len(), "{input}");
+
+        // The date field is bytes 16 to 28 of each header.
+        let headers = archive_headers(&new);
+        let is_date =
+            |i: usize| headers.iter().any(|&(offset, _)| (offset + 16..offset + 28).contains(&i));
+        let changed: Vec<usize> = (0..old.len()).filter(|&i| old[i] != new[i]).collect();
+        assert!(!changed.is_empty(), "{input}");
+        assert!(changed.iter().all(|&i| is_date(i)), "{input}: {changed:x?}");
+        for (_, header) in headers {
+            let name = String::from_utf8_lossy(&header[..16]);
+            if !matches!(name.trim_end(), "/" | "//") {
+                assert_eq!(&header[16..28], b"1700000000  ", "{input}: {name}");
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::{failure, fixture, success, temp_dir, winlib};
+
+#[test]
+fn symbols_show_the_alias() {
+    let symbols = success(winlib().arg("symbols").arg(fixture("objects.lib")));
+    assert!(symbols.contains("weak.obj: weak foo -> bar\n"), "{symbols}");
+    assert!(symbols.contains("weak.obj: bar\n"), "{symbols}");
+}
+
+#[test]
+fn weak_externals_are_not_duplicates() {
+    // foo.obj defines both `foo` and `bar`, weak.obj defines `bar` and a weak `foo`.
+    let mut list = winlib();
+    list.arg("list").arg("--check-duplicates").arg(fixture("objects.lib"));
+    let stderr = failure(&mut list);
+    assert!(stderr.contains("duplicate symbol bar "), "{stderr}");
+    assert!(!stderr.contains("duplicate symbol foo "), "{stderr}");
+}
+
+#[test]
+fn weak_only_members_are_empty() {
+    let lib = temp_dir("weak-exclude-empty").join("out.lib");
+    success(
+        winlib()
+            .arg("create")
+            .arg("--from")
+            .arg(fixture("weak.lib"))
+            .arg(&lib)
+            .arg("--exclude-empty"),
+    );
+    let members = success(winlib().arg("list").arg(&lib));
+    assert!(members.contains("baz.obj"), "{members}");
+    assert!(!members.contains("weak-only.obj"), "{members}");
+}
+
+#[test]
+fn def_skips_weak_externals() {
+    let def = success(winlib().arg("def").arg(fixture("weak.lib")));
+    assert!(def.contains("    baz\n"), "{def}");
+    assert!(!def.contains("wfn"), "{def}");
+}